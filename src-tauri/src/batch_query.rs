@@ -0,0 +1,629 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+use tokio::net::UdpSocket;
+use tokio::sync::{oneshot, OnceCell, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Instant};
+
+use crate::ping::{self, PingStats};
+use crate::query::{
+    self, OutstandingRequest, Player, Query, QueryOutcome, QueryPacket, ServerQueryResponse,
+};
+use crate::retransmit::{self, QueryBudget};
+use crate::{constants::*, errors::*};
+
+type PendingKey = (SocketAddr, u8);
+type PendingMap = HashMap<PendingKey, Vec<Waiter>>;
+
+struct Waiter {
+    request: OutstandingRequest,
+    tx: oneshot::Sender<(Vec<u8>, Instant)>,
+}
+
+static BATCH_ENGINE: OnceCell<BatchQueryEngine> = OnceCell::const_new();
+
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct BatchEndpoint {
+    pub ip: String,
+    pub port: i32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BatchQueryResult {
+    pub batch_id: u32,
+    pub ip: String,
+    pub port: i32,
+    pub result: ServerQueryResponse,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchQueryParams {
+    pub info: bool,
+    pub extra_info: bool,
+    pub players: bool,
+    pub rules: bool,
+    pub ping: bool,
+    /// Ping probes per server, `PING_PROBE_COUNT` if unset.
+    #[serde(default)]
+    pub ping_probes: Option<u32>,
+    /// Time budget per server in milliseconds, `QUERY_DEFAULT_BUDGET_MS` if unset.
+    #[serde(default)]
+    pub budget_ms: Option<u64>,
+}
+
+/// Queries many servers over one shared UDP socket per address family.
+/// Replies are routed back to the waiting request by their source address and
+/// opcode.
+pub struct BatchQueryEngine {
+    socket_v4: Arc<UdpSocket>,
+    /// `None` if this machine has no IPv6 support.
+    socket_v6: Option<Arc<UdpSocket>>,
+    pending: Arc<Mutex<PendingMap>>,
+    in_flight: Semaphore,
+    /// Limits the servers of `query_servers` batches queried at once, so the
+    /// ones still queued don't run out their budget before sending anything.
+    servers: Semaphore,
+    /// Limits concurrent DNS lookups, each runs on a blocking thread.
+    resolving: Semaphore,
+    dropped: Arc<AtomicU64>,
+}
+
+impl BatchQueryEngine {
+    pub async fn shared() -> Result<&'static BatchQueryEngine> {
+        BATCH_ENGINE.get_or_try_init(Self::new).await
+    }
+
+    async fn new() -> Result<Self> {
+        Self::with_limits(BATCH_QUERY_MAX_SERVERS, BATCH_QUERY_MAX_IN_FLIGHT).await
+    }
+
+    async fn with_limits(max_servers: usize, max_in_flight: usize) -> Result<Self> {
+        let socket_v4 = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| LauncherError::Network(format!("Failed to bind socket: {}", e)))?;
+
+        let socket_v6 = match UdpSocket::bind("[::]:0").await {
+            Ok(socket) => Some(Arc::new(socket)),
+            Err(e) => {
+                log::info!("IPv6 batch query socket unavailable: {}", e);
+                None
+            }
+        };
+
+        let engine = Self {
+            socket_v4: Arc::new(socket_v4),
+            socket_v6,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            in_flight: Semaphore::new(max_in_flight),
+            servers: Semaphore::new(max_servers),
+            resolving: Semaphore::new(BATCH_QUERY_MAX_RESOLVING),
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+
+        for socket in std::iter::once(&engine.socket_v4).chain(&engine.socket_v6) {
+            tokio::spawn(Self::route_replies(
+                socket.clone(),
+                engine.pending.clone(),
+                engine.dropped.clone(),
+            ));
+        }
+
+        Ok(engine)
+    }
+
+    async fn route_replies(
+        socket: Arc<UdpSocket>,
+        pending: Arc<Mutex<PendingMap>>,
+        dropped: Arc<AtomicU64>,
+    ) {
+        let mut buf = [0; UDP_BUFFER_SIZE];
+        loop {
+            let (amt, src) = match socket.recv_from(&mut buf).await {
+                Ok(r) => r,
+                Err(e) => {
+                    // ICMP port unreachable from a dead server surfaces here on some
+                    // platforms, it must not take the whole engine down
+                    log::debug!("Batch query socket receive failed: {}", e);
+                    continue;
+                }
+            };
+
+            let received_at = Instant::now();
+            let datagram = &buf[..amt];
+            let mut matched = Vec::new();
+            if amt >= QUERY_HEADER_LENGTH {
+                let mut map = match pending.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+
+                let key = (src, datagram[QUERY_HEADER_LENGTH - 1]);
+                if let Some(waiters) = map.get_mut(&key) {
+                    let mut i = 0;
+                    while i < waiters.len() {
+                        if waiters[i].request.matches(datagram) {
+                            matched.push(waiters.swap_remove(i));
+                        } else {
+                            i += 1;
+                        }
+                    }
+                    if waiters.is_empty() {
+                        map.remove(&key);
+                    }
+                }
+            }
+
+            if matched.is_empty() {
+                dropped.fetch_add(1, Ordering::Relaxed);
+                log::debug!(
+                    "Dropped {} byte datagram from {} with no matching request",
+                    amt,
+                    src
+                );
+                continue;
+            }
+
+            for waiter in matched {
+                let body = datagram[QUERY_HEADER_LENGTH..].to_vec();
+                let _ = waiter.tx.send((body, received_at));
+            }
+        }
+    }
+
+    /// Number of datagrams received that did not answer any pending request.
+    pub fn dropped_datagrams(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn register(
+        &self,
+        key: PendingKey,
+        request: OutstandingRequest,
+    ) -> oneshot::Receiver<(Vec<u8>, Instant)> {
+        let (tx, rx) = oneshot::channel();
+        self.lock_pending()
+            .entry(key)
+            .or_default()
+            .push(Waiter { request, tx });
+        rx
+    }
+
+    /// Drops waiters whose receiver is gone, called after a request gave up.
+    fn forget(&self, key: &PendingKey) {
+        let mut map = self.lock_pending();
+        if let Some(waiters) = map.get_mut(key) {
+            waiters.retain(|w| !w.tx.is_closed());
+            if waiters.is_empty() {
+                map.remove(key);
+            }
+        }
+    }
+
+    fn socket_for(&self, target: SocketAddr) -> Option<&UdpSocket> {
+        match target {
+            SocketAddr::V4(_) => Some(&self.socket_v4),
+            SocketAddr::V6(_) => self.socket_v6.as_deref(),
+        }
+    }
+
+    async fn send_packet(&self, request: &OutstandingRequest, target: SocketAddr) -> Result<()> {
+        let socket = self.socket_for(target).ok_or_else(|| {
+            LauncherError::Network("IPv6 is not available on this machine".to_string())
+        })?;
+        socket
+            .send_to(&request.packet(), target)
+            .await
+            .map(|_| ())
+            .map_err(|e| LauncherError::Network(format!("Failed to send packet: {}", e)))
+    }
+
+    /// Sends a request and waits for the matching reply, retransmitting with
+    /// exponential backoff while `budget` lasts. Returns the packet body. The
+    /// caller holds its `in_flight` permit.
+    async fn request(
+        &self,
+        target: SocketAddr,
+        query_type: char,
+        budget: &QueryBudget,
+    ) -> Result<Vec<u8>> {
+        let key = (target, query_type as u8);
+        let request = OutstandingRequest::new(target, query_type);
+        let mut rx = self.register(key, request);
+
+        let mut rto = retransmit::initial_rto(target);
+        let mut result = Err(LauncherError::Timeout(format!(
+            "No reply to '{}' query",
+            query_type
+        )));
+        for attempt in 0..QUERY_MAX_ATTEMPTS {
+            let Some(left) = budget.remaining() else {
+                break;
+            };
+
+            let sent_at = Instant::now();
+            if let Err(e) = self.send_packet(&request, target).await {
+                result = Err(e);
+                break;
+            }
+
+            match timeout(rto.min(left), &mut rx).await {
+                Ok(Ok((body, received_at))) => {
+                    if attempt == 0 {
+                        retransmit::record_sample(target, received_at - sent_at);
+                    }
+                    result = Ok(body);
+                    break;
+                }
+                Ok(Err(_)) => {
+                    result = Err(LauncherError::Network("Query cancelled".to_string()));
+                    break;
+                }
+                Err(_) => rto = retransmit::backoff(rto),
+            }
+        }
+
+        if result.is_err() {
+            if let Err(LauncherError::Timeout(_)) = result {
+                budget.mark_exhausted();
+            }
+            drop(rx);
+            self.forget(&key);
+        }
+
+        result
+    }
+
+    /// Sends a single ping probe and resolves with its round trip time. Probes
+    /// are never retransmitted, a lost probe counts as packet loss. The caller
+    /// holds its `in_flight` permit.
+    async fn probe(&self, target: SocketAddr, budget: &QueryBudget) -> Result<Duration> {
+        let wait = budget
+            .remaining()
+            .ok_or_else(|| LauncherError::Timeout("Query budget exhausted".to_string()))?
+            .min(Duration::from_millis(PING_PROBE_TIMEOUT_MS));
+
+        let key = (target, QUERY_TYPE_PING as u8);
+        let request = OutstandingRequest::new(target, QUERY_TYPE_PING);
+        let rx = self.register(key, request);
+
+        let sent_at = Instant::now();
+        let result = match self.send_packet(&request, target).await {
+            Ok(_) => match timeout(wait, rx).await {
+                Ok(Ok((_, received_at))) => {
+                    let rtt = received_at - sent_at;
+                    retransmit::record_sample(target, rtt);
+                    Ok(rtt)
+                }
+                Ok(Err(_)) => Err(LauncherError::Network("Query cancelled".to_string())),
+                Err(_) => Err(LauncherError::Timeout("Ping probe lost".to_string())),
+            },
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            self.forget(&key);
+        }
+
+        result
+    }
+
+    async fn request_parsed(
+        &self,
+        target: SocketAddr,
+        query_type: char,
+        budget: &QueryBudget,
+    ) -> Result<QueryPacket> {
+        let body = self.request(target, query_type, budget).await?;
+        Query::parse_packet(query_type, Cursor::new(body))
+    }
+
+    /// Same as [`Query::request_players`]: tries the detailed ('d') player
+    /// list first and falls back to the basic ('c') one.
+    async fn request_players(
+        &self,
+        target: SocketAddr,
+        budget: &QueryBudget,
+    ) -> Result<Vec<Player>> {
+        if !query::detailed_players_supported(target) {
+            return self
+                .request_parsed(target, QUERY_TYPE_PLAYERS, budget)
+                .await
+                .and_then(QueryPacket::into_players);
+        }
+
+        let detailed_budget = budget.limited(Duration::from_millis(DETAILED_PLAYERS_BUDGET_MS));
+        match self
+            .request_parsed(target, QUERY_TYPE_DETAILED_PLAYERS, &detailed_budget)
+            .await
+            .and_then(QueryPacket::into_players)
+        {
+            Ok(players) => Ok(players),
+            Err(
+                LauncherError::Timeout(_)
+                | LauncherError::Parse(_)
+                | LauncherError::InvalidInput(_),
+            ) => {
+                let players = self
+                    .request_parsed(target, QUERY_TYPE_PLAYERS, budget)
+                    .await
+                    .and_then(QueryPacket::into_players)?;
+                query::mark_detailed_players_unsupported(target);
+                Ok(players)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, PendingMap> {
+        match self.pending.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Runs every requested query type against one server concurrently.
+    pub async fn query_endpoint(
+        &self,
+        ip: &str,
+        port: i32,
+        params: BatchQueryParams,
+    ) -> Result<ServerQueryResponse> {
+        let resolved = {
+            let _permit = self
+                .resolving
+                .acquire()
+                .await
+                .map_err(|e| LauncherError::InternalError(e.to_string()))?;
+            query::resolve(ip, port).await?
+        };
+        let target = resolved
+            .into_iter()
+            .find(|addr| self.socket_for(*addr).is_some())
+            .ok_or_else(|| {
+                LauncherError::Network("IPv6 is not available on this machine".to_string())
+            })?;
+        let key = format!("{}:{}", ip, port);
+        let probes = params.ping_probes.unwrap_or(PING_PROBE_COUNT).max(1);
+        let request_extra_info = params.extra_info && {
+            let now_secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            query::should_request_extra_info(&key, now_secs)
+        };
+
+        // take a permit for every request up front and only then start the
+        // budget, time spent waiting for permits must not count against it
+        let sections = [
+            params.info,
+            params.players,
+            params.rules,
+            request_extra_info,
+        ];
+        let mut requests = sections.iter().filter(|&&requested| requested).count() as u32;
+        if params.ping {
+            requests += probes;
+        }
+        let _permits = self
+            .in_flight
+            .acquire_many(requests)
+            .await
+            .map_err(|e| LauncherError::InternalError(e.to_string()))?;
+        let budget = QueryBudget::from_millis(params.budget_ms);
+
+        let (info, players, rules, extra_info, ping_stats) = tokio::join!(
+            async {
+                if params.info {
+                    Some(
+                        self.request_parsed(target, QUERY_TYPE_INFO, &budget)
+                            .await
+                            .and_then(QueryPacket::into_info)
+                            .into(),
+                    )
+                } else {
+                    None
+                }
+            },
+            async {
+                if params.players {
+                    Some(self.request_players(target, &budget).await.into())
+                } else {
+                    None
+                }
+            },
+            async {
+                if params.rules {
+                    Some(
+                        self.request_parsed(target, QUERY_TYPE_RULES, &budget)
+                            .await
+                            .and_then(QueryPacket::into_rules)
+                            .into(),
+                    )
+                } else {
+                    None
+                }
+            },
+            async {
+                if request_extra_info {
+                    Some(
+                        self.request_parsed(target, QUERY_TYPE_EXTRA_INFO, &budget)
+                            .await
+                            .and_then(QueryPacket::into_extra_info)
+                            .into(),
+                    )
+                } else {
+                    None
+                }
+            },
+            async {
+                if params.ping {
+                    Some(ping::measure(probes, || self.probe(target, &budget)).await)
+                } else {
+                    None
+                }
+            },
+        );
+
+        let response = ServerQueryResponse {
+            info,
+            extra_info,
+            players,
+            rules,
+            ping: ping_stats.as_ref().map(PingStats::display_ms),
+            ping_stats,
+            ..Default::default()
+        };
+        Ok(response)
+    }
+
+    /// [`Self::query_endpoint`] for a server of a batch. Waits for one of the
+    /// `servers` slots first, the query budget only starts once it has one.
+    async fn query_batched(
+        &self,
+        ip: &str,
+        port: i32,
+        params: BatchQueryParams,
+    ) -> Result<ServerQueryResponse> {
+        let _permit = self
+            .servers
+            .acquire()
+            .await
+            .map_err(|e| LauncherError::InternalError(e.to_string()))?;
+        self.query_endpoint(ip, port, params).await
+    }
+}
+
+/// Queries a list of servers and emits a `server-query-result` event for each
+/// one as soon as its replies are in. Resolves with the number of servers
+/// queried once the whole batch is done.
+#[tauri::command]
+pub async fn query_servers(
+    app_handle: tauri::AppHandle,
+    batch_id: u32,
+    servers: Vec<BatchEndpoint>,
+    params: BatchQueryParams,
+) -> Result<usize> {
+    let engine = BatchQueryEngine::shared().await?;
+
+    let mut seen = HashSet::new();
+    let mut tasks = JoinSet::new();
+    for server in servers {
+        if !seen.insert(server.clone()) {
+            continue;
+        }
+
+        let handle = app_handle.clone();
+        tasks.spawn(async move {
+            let result = match engine.query_batched(&server.ip, server.port, params).await {
+                Ok(r) => r,
+                Err(e) => ServerQueryResponse {
+                    info: params.info.then(|| QueryOutcome::Error(e.into())),
+                    ping: params.ping.then_some(PING_TIMEOUT),
+                    ..Default::default()
+                },
+            };
+
+            let _ = handle.emit_all(
+                "server-query-result",
+                BatchQueryResult {
+                    batch_id,
+                    ip: server.ip,
+                    port: server.port,
+                    result,
+                },
+            );
+        });
+    }
+
+    let count = tasks.len();
+    while tasks.join_next().await.is_some() {}
+
+    log::debug!(
+        "Batch {} queried {} servers, {} stray datagrams dropped so far",
+        batch_id,
+        count,
+        engine.dropped_datagrams()
+    );
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY_DELAY_MS: u64 = 200;
+
+    /// Answers every request with an empty rules list after `REPLY_DELAY_MS`.
+    async fn spawn_slow_server() -> SocketAddr {
+        let server = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; UDP_BUFFER_SIZE];
+            loop {
+                let Ok((amt, from)) = server.recv_from(&mut buf).await else {
+                    return;
+                };
+                let mut reply = buf[..QUERY_HEADER_LENGTH.min(amt)].to_vec();
+                reply.extend_from_slice(&[0, 0]);
+                let server = server.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(REPLY_DELAY_MS)).await;
+                    let _ = server.send_to(&reply, from).await;
+                });
+            }
+        });
+        addr
+    }
+
+    /// Queries more servers than the engine lets through at once, with a
+    /// budget that only covers a couple of reply delays.
+    async fn query_queued_servers(max_servers: usize, max_in_flight: usize) {
+        let engine: &'static BatchQueryEngine = Box::leak(Box::new(
+            BatchQueryEngine::with_limits(max_servers, max_in_flight)
+                .await
+                .unwrap(),
+        ));
+        let params = BatchQueryParams {
+            info: false,
+            extra_info: false,
+            players: false,
+            rules: true,
+            ping: false,
+            ping_probes: None,
+            budget_ms: Some(REPLY_DELAY_MS * 2),
+        };
+
+        let mut tasks = JoinSet::new();
+        for _ in 0..max_servers.min(max_in_flight) * 4 {
+            let addr = spawn_slow_server().await;
+            tasks.spawn(async move {
+                engine
+                    .query_batched("127.0.0.1", addr.port() as i32, params)
+                    .await
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let response = joined.unwrap().unwrap();
+            assert!(
+                matches!(response.rules, Some(QueryOutcome::Ok(_))),
+                "a queued server timed out"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_servers_keep_their_budget() {
+        query_queued_servers(2, 2).await;
+    }
+
+    #[tokio::test]
+    async fn queued_requests_keep_their_budget() {
+        query_queued_servers(BATCH_QUERY_MAX_SERVERS, 1).await;
+    }
+}
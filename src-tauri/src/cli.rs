@@ -1,7 +1,30 @@
-use crate::{constants::*, errors::*, validation};
+use crate::favorites::{self, FavoriteServer, FavoritesFile};
+use crate::injector::run_samp;
+use crate::ping::{self, PingStats};
+use crate::query::{
+    Query, QueryError, QueryErrorKind, QueryOutcome, QueryPacket, ServerQueryResponse,
+};
+use crate::retransmit::QueryBudget;
+use crate::uri::{self, ClientVersion, ServerUri};
+use crate::{commands, constants::*, errors::*, helpers, samp, schemes, settings, validation};
 use gumdrop::Options;
-use std::process::exit;
+use log::info;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
+/// Names of the subcommands, see [`Command`].
+pub const COMMAND_NAMES: &[&str] = &[
+    "launch",
+    "query",
+    "favorites",
+    "verify",
+    "extract",
+    "uri",
+    "schemes",
+];
+
+// passing the `launch` options without a command launches the game too,
+// that is what existing shortcuts do
 #[derive(Debug, Options)]
 pub struct CliArgs {
     #[options(no_short, help = "print help message")]
@@ -13,7 +36,52 @@ pub struct CliArgs {
     #[options(help = "target server port")]
     pub port: Option<i32>,
 
-    #[options(help = "target server password")]
+    #[options(short = "P", help = "target server password")]
+    pub password: Option<String>,
+
+    #[options(help = "nickname to join server with")]
+    pub name: Option<String>,
+
+    #[options(help = "game path to use for both game executable and samp.dll")]
+    pub gamepath: Option<String>,
+
+    #[options(help = "disable omp-client injection")]
+    pub no_omp: bool,
+
+    #[options(command)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Options)]
+pub enum Command {
+    #[options(help = "launch the game and join a server")]
+    Launch(LaunchArgs),
+    #[options(help = "query a server and print what it reports")]
+    Query(QueryArgs),
+    #[options(help = "list, import or export favorite servers")]
+    Favorites(FavoritesArgs),
+    #[options(help = "check the samp resource files against their checksums")]
+    Verify(VerifyArgs),
+    #[options(help = "extract the samp resource archive")]
+    Extract(ExtractArgs),
+    #[options(help = "open an omp:// or samp:// link")]
+    Uri(UriArgs),
+    #[options(help = "show or remove the omp:// and samp:// link handlers")]
+    Schemes(SchemesArgs),
+}
+
+#[derive(Debug, Clone, Options)]
+pub struct LaunchArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "target server IP address")]
+    pub host: Option<String>,
+
+    #[options(help = "target server port")]
+    pub port: Option<i32>,
+
+    #[options(short = "P", help = "target server password")]
     pub password: Option<String>,
 
     #[options(help = "nickname to join server with")]
@@ -26,7 +94,223 @@ pub struct CliArgs {
     pub no_omp: bool,
 }
 
+#[derive(Debug, Options)]
+pub struct QueryArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "server IP address or hostname")]
+    pub host: Option<String>,
+
+    #[options(help = "server port")]
+    pub port: Option<i32>,
+
+    #[options(no_short, help = "also request the player list")]
+    pub players: bool,
+
+    #[options(no_short, help = "also request the server rules")]
+    pub rules: bool,
+
+    #[options(no_short, help = "print the result as JSON")]
+    pub json: bool,
+
+    #[options(
+        no_short,
+        help = "time for each part of the query, retries included, in milliseconds"
+    )]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Options)]
+pub struct FavoritesArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(command, required)]
+    pub command: Option<FavoritesCommand>,
+}
+
+#[derive(Debug, Options)]
+pub enum FavoritesCommand {
+    #[options(help = "list the launcher's favorites")]
+    List(FavoritesListArgs),
+    #[options(help = "add the servers of a favorites file to the launcher's favorites")]
+    Import(FavoritesFileArgs),
+    #[options(help = "write the launcher's favorites to a favorites file")]
+    Export(FavoritesExportArgs),
+}
+
+#[derive(Debug, Options)]
+pub struct FavoritesListArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(no_short, help = "print the list as JSON")]
+    pub json: bool,
+
+    #[options(no_short, help = "list the favorites of the SA-MP client instead")]
+    pub samp: bool,
+}
+
+#[derive(Debug, Options)]
+pub struct FavoritesFileArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(free, required, help = "path of the favorites file")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Options)]
+pub struct FavoritesExportArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(no_short, help = "export the favorites of the SA-MP client instead")]
+    pub samp: bool,
+
+    #[options(free, required, help = "path of the favorites file")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Options)]
+pub struct VerifyArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "samp resource directory, defaults to the launcher's")]
+    pub dir: Option<String>,
+
+    #[options(no_short, help = "print the result as JSON")]
+    pub json: bool,
+}
+
+#[derive(Debug, Options)]
+pub struct ExtractArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "archive to extract, defaults to the launcher's samp_clients.7z")]
+    pub archive: Option<String>,
+
+    #[options(help = "directory to extract to, defaults to the launcher's samp directory")]
+    pub output: Option<String>,
+
+    #[options(
+        help = "also copy the shared samp files into this game directory, as the launcher does"
+    )]
+    pub gamepath: Option<String>,
+}
+
+#[derive(Debug, Options)]
+pub struct UriArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "launch the game right away instead of opening the launcher")]
+    pub direct: bool,
+
+    #[options(free, required, help = "omp:// or samp:// link")]
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Options)]
+pub struct SchemesArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(command, required)]
+    pub command: Option<SchemesCommand>,
+}
+
+#[derive(Debug, Options)]
+pub enum SchemesCommand {
+    #[options(help = "show which program opens each link scheme")]
+    Status(SchemesStatusArgs),
+    #[options(help = "stop handling links and remove the launcher's registrations")]
+    Unregister(SchemesUnregisterArgs),
+}
+
+#[derive(Debug, Options)]
+pub struct SchemesStatusArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+
+    #[options(no_short, help = "print the status as JSON")]
+    pub json: bool,
+}
+
+#[derive(Debug, Options)]
+pub struct SchemesUnregisterArgs {
+    #[options(no_short, help = "print help message")]
+    pub help: bool,
+}
+
+/// Everything [`start_game`] needs, gathered from the command line or a link.
+struct GameLaunch {
+    name: String,
+    host: String,
+    port: i32,
+    gamepath: String,
+    dll: PathBuf,
+    password: String,
+    custom_game_exe: String,
+    no_omp: bool,
+}
+
+/// What `query --json` prints.
+#[derive(Serialize)]
+struct QueryReport {
+    host: String,
+    port: i32,
+    /// The address that was queried, `None` if the host could not be resolved.
+    address: Option<String>,
+    #[serde(flatten)]
+    response: ServerQueryResponse,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum FileStatus {
+    Ok,
+    Mismatch,
+    Missing,
+}
+
+/// One line of `verify`'s output.
+#[derive(Serialize)]
+struct VerifiedFile {
+    /// Relative to the samp resource directory.
+    path: &'static str,
+    status: FileStatus,
+    expected: &'static str,
+    actual: Option<String>,
+}
+
 impl CliArgs {
+    pub fn validate(&self) -> Result<()> {
+        self.launch_args().validate()
+    }
+
+    /// The top level launch options as `launch` arguments.
+    pub fn launch_args(&self) -> LaunchArgs {
+        LaunchArgs {
+            help: self.help,
+            host: self.host.clone(),
+            port: self.port,
+            password: self.password.clone(),
+            name: self.name.clone(),
+            gamepath: self.gamepath.clone(),
+            no_omp: self.no_omp,
+        }
+    }
+
+    pub fn has_game_launch_args(&self) -> bool {
+        self.launch_args().is_complete()
+    }
+}
+
+impl LaunchArgs {
     pub fn validate(&self) -> Result<()> {
         if let Some(ref host) = self.host {
             validation::validate_hostname(host)?;
@@ -55,28 +339,7 @@ impl CliArgs {
         Ok(())
     }
 
-    pub fn print_help_and_exit(program_name: &str) -> ! {
-        // Using println! here is appropriate for CLI help output
-        println!(
-            "Open Multiplayer Launcher
-
-Usage: {} [OPTIONS]
-
-Options:
-      --help
-  -h, --host <HOST>          Server IP
-  -p, --port <PORT>          Server port
-  -P, --password <PASSWORD>  Server password
-  -n, --name <NAME>          Nickname
-  -g, --gamepath <GAMEPATH>  Game path
-      --no-omp               Disable omp-client injection
-            ",
-            program_name
-        );
-        exit(0)
-    }
-
-    pub fn has_game_launch_args(&self) -> bool {
+    pub fn is_complete(&self) -> bool {
         self.host.is_some() && self.name.is_some() && self.port.is_some() && self.gamepath.is_some()
     }
 
@@ -87,3 +350,628 @@ Options:
         }
     }
 }
+
+/// Prints the generated help of the innermost command given in `args`.
+pub fn print_help(args: &CliArgs, program_name: &str) {
+    let mut command: &dyn Options = args;
+    let mut command_path = String::new();
+    while let Some(sub) = command.command() {
+        command = sub;
+        if let Some(name) = sub.command_name() {
+            command_path.push(' ');
+            command_path.push_str(name);
+        }
+    }
+
+    if command_path.is_empty() {
+        println!("Open Multiplayer Launcher\n");
+    }
+    println!("Usage: {}{} [OPTIONS]\n", program_name, command_path);
+    println!("{}", command.self_usage());
+    if let Some(commands) = command.self_command_list() {
+        println!("\nCommands:\n{}", commands);
+    }
+    if !command_path.is_empty() {
+        return;
+    }
+    println!(
+        "\nExit codes:
+  {}  success
+  {}  any other error
+  {}  invalid arguments
+  {}  the server did not answer in time
+  {}  the host could not be resolved
+  {}  the server sent an invalid response
+  {}  the server could not be reached
+  {}  resource files are missing or damaged
+  {}  the running launcher rejected the request
+  {}  the running launcher did not answer",
+        CLI_EXIT_SUCCESS,
+        CLI_EXIT_ERROR,
+        CLI_EXIT_USAGE,
+        CLI_EXIT_TIMEOUT,
+        CLI_EXIT_RESOLVE,
+        CLI_EXIT_BAD_RESPONSE,
+        CLI_EXIT_UNREACHABLE,
+        CLI_EXIT_VERIFY_FAILED,
+        CLI_EXIT_HANDOFF_REJECTED,
+        CLI_EXIT_HANDOFF_FAILED
+    );
+}
+
+/// Whether `arg` names a subcommand, so a bad invocation of it can be reported
+/// instead of starting the launcher.
+pub fn is_command(arg: &str) -> bool {
+    COMMAND_NAMES.contains(&arg)
+}
+
+/// Runs `command` and returns the process exit code. `uri` is only handled
+/// here with `--direct`, otherwise it opens the launcher window.
+pub async fn run_command(command: &Command) -> i32 {
+    let result = match command {
+        Command::Launch(args) => run_launch(args).await,
+        Command::Query(args) => return run_query(args).await,
+        Command::Favorites(args) => run_favorites(args),
+        Command::Verify(args) => return run_verify(args),
+        Command::Extract(args) => run_extract(args),
+        Command::Uri(args) if args.direct => run_uri(args).await,
+        Command::Uri(_) => Ok(()),
+        Command::Schemes(args) => run_schemes(args),
+    };
+
+    match result {
+        Ok(()) => CLI_EXIT_SUCCESS,
+        Err(LauncherError::InvalidInput(msg)) => {
+            eprintln!("{}", msg);
+            CLI_EXIT_USAGE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            CLI_EXIT_ERROR
+        }
+    }
+}
+
+/// Launches the game and injects the omp client unless `no_omp` is set.
+pub async fn launch(args: &LaunchArgs) -> Result<()> {
+    let (Some(host), Some(port), Some(name), Some(gamepath)) =
+        (&args.host, args.port, &args.name, &args.gamepath)
+    else {
+        return Err(LauncherError::InvalidInput(
+            "--host, --port, --name and --gamepath are required".to_string(),
+        ));
+    };
+
+    start_game(&GameLaunch {
+        name: name.clone(),
+        host: host.clone(),
+        port,
+        gamepath: gamepath.clone(),
+        dll: Path::new(gamepath).join(SAMP_DLL),
+        password: args.get_password(),
+        custom_game_exe: String::new(),
+        no_omp: args.no_omp,
+    })
+    .await
+}
+
+/// Launches the game for a parsed link. What the link leaves out is taken
+/// from the launcher's settings, then from the SA-MP client's.
+pub async fn launch_uri(uri: &ServerUri, no_omp: bool) -> Result<()> {
+    let settings = settings::load();
+    let non_empty = |value: String| Some(value).filter(|v| !v.trim().is_empty());
+
+    let name = uri
+        .nickname
+        .clone()
+        .or_else(|| non_empty(settings.nick_name.clone()))
+        .or_else(|| non_empty(samp::get_nickname()))
+        .ok_or_else(|| {
+            LauncherError::InvalidInput(
+                "No nickname is set, add one to the link or set it in the launcher".to_string(),
+            )
+        })?;
+    let gamepath = non_empty(settings.gtasa_path.clone())
+        .or_else(|| non_empty(samp::get_gtasa_path()))
+        .ok_or_else(|| {
+            LauncherError::InvalidInput(
+                "The GTA San Andreas path is not set, set it in the launcher".to_string(),
+            )
+        })?;
+
+    let args = LaunchArgs {
+        help: false,
+        host: Some(uri.host.clone()),
+        port: Some(uri.port as i32),
+        password: uri.password.clone(),
+        name: Some(name.clone()),
+        gamepath: Some(gamepath.clone()),
+        no_omp,
+    };
+    args.validate()?;
+
+    let version = uri
+        .version
+        .or_else(|| settings.samp_version.parse().ok())
+        .unwrap_or(ClientVersion::Custom);
+    let dll = match version.dir() {
+        Some(dir) => {
+            let samp_dir = samp_dir()?;
+            copy_missing_shared_files(&samp_dir, &gamepath)?;
+            samp_dir.join(dir).join(SAMP_DLL)
+        }
+        None => Path::new(&gamepath).join(SAMP_DLL),
+    };
+    if !dll.is_file() {
+        return Err(LauncherError::NotFound(format!(
+            "{} not found at: {}",
+            SAMP_DLL,
+            dll.display()
+        )));
+    }
+
+    start_game(&GameLaunch {
+        name,
+        host: uri.host.clone(),
+        port: uri.port as i32,
+        gamepath,
+        dll,
+        password: args.get_password(),
+        custom_game_exe: settings.custom_game_exe,
+        no_omp,
+    })
+    .await
+}
+
+/// Copies the shared samp files into the game directory if any of them is
+/// missing there, like the launcher does before starting a bundled version.
+fn copy_missing_shared_files(samp_dir: &Path, gamepath: &str) -> Result<()> {
+    let shared_prefix = format!("{}/", SAMP_SHARED_DIR);
+    let missing = SAMP_RESOURCE_CHECKSUMS
+        .iter()
+        .filter_map(|(path, _)| path.strip_prefix(&shared_prefix))
+        .any(|path| !Path::new(gamepath).join(path).exists());
+
+    if missing {
+        info!("Missing shared samp files, copying them into {}", gamepath);
+        helpers::copy_files(samp_dir.join(SAMP_SHARED_DIR), gamepath)?;
+    }
+    Ok(())
+}
+
+async fn start_game(game: &GameLaunch) -> Result<()> {
+    let omp_client_path = helpers::local_data_dir()?.join("omp").join(OMP_CLIENT_DLL);
+    let omp_client_path = omp_client_path
+        .to_str()
+        .ok_or(LauncherError::InternalError(
+            "Invalid data directory path".to_string(),
+        ))?;
+    let omp_path = if game.no_omp { "" } else { omp_client_path };
+    let dll_path = game.dll.to_str().ok_or(LauncherError::InternalError(
+        "Invalid samp.dll path".to_string(),
+    ))?;
+
+    // resolve hostname to ipv4 so the game does not truncate hyphenated hosts
+    let resolved_host = helpers::resolve_hostname_to_ipv4(&game.host).unwrap_or_else(|e| {
+        info!(
+            "Failed to resolve hostname '{}', using raw value: {}",
+            game.host, e
+        );
+        game.host.clone()
+    });
+
+    run_samp(
+        &game.name,
+        &resolved_host,
+        game.port,
+        &game.gamepath,
+        dll_path,
+        omp_path,
+        &game.password,
+        &game.custom_game_exe,
+    )
+    .await
+    .map_err(|e| LauncherError::InternalError(e.to_string()))?;
+
+    info!("Successfully launched game from command line");
+    Ok(())
+}
+
+async fn run_launch(args: &LaunchArgs) -> Result<()> {
+    args.validate()?;
+    launch(args).await
+}
+
+async fn run_uri(args: &UriArgs) -> Result<()> {
+    let link = args
+        .uri
+        .as_deref()
+        .ok_or_else(|| LauncherError::InvalidInput("uri: a link is required".to_string()))?;
+    let no_omp = crate::NO_OMP_FLAG.lock().is_ok_and(|flag| *flag);
+    launch_uri(&uri::parse(link)?, no_omp).await
+}
+
+fn samp_dir() -> Result<PathBuf> {
+    Ok(helpers::local_data_dir()?.join(SAMP_RESOURCE_DIR))
+}
+
+fn run_favorites(args: &FavoritesArgs) -> Result<()> {
+    match &args.command {
+        Some(FavoritesCommand::List(list)) => {
+            let servers = favorites_of(list.samp)?;
+            if list.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&FavoritesFile::new(servers))?
+                );
+                return Ok(());
+            }
+
+            for server in &servers {
+                println!(
+                    "{}:{}  {}{}",
+                    server.ip,
+                    server.port,
+                    server.name,
+                    if server.password.is_empty() {
+                        ""
+                    } else {
+                        "  (password)"
+                    }
+                );
+            }
+            println!("{} favorite servers", servers.len());
+        }
+        Some(FavoritesCommand::Import(file)) => {
+            let path = required_path(file.file.as_deref())?;
+            let servers: Vec<FavoriteServer> = FavoritesFile::read(path)?.servers;
+            let count = servers.len();
+            favorites::queue_import(servers)?;
+            println!(
+                "{} servers will be added to the favorites when the launcher opens",
+                count
+            );
+        }
+        Some(FavoritesCommand::Export(export)) => {
+            let path = required_path(export.file.as_deref())?;
+            let servers = favorites_of(export.samp)?;
+            let count = servers.len();
+            FavoritesFile::new(servers).write(path)?;
+            println!("Exported {} favorite servers to {}", count, path.display());
+        }
+        None => {
+            return Err(LauncherError::InvalidInput(
+                "favorites: a command is required".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run_schemes(args: &SchemesArgs) -> Result<()> {
+    match &args.command {
+        Some(SchemesCommand::Status(status)) => {
+            let schemes = schemes::status()?;
+            if status.json {
+                println!("{}", serde_json::to_string_pretty(&schemes)?);
+                return Ok(());
+            }
+
+            for scheme in &schemes {
+                println!(
+                    "{:<5} {:<9} {}{}",
+                    scheme.scheme,
+                    if scheme.claimed {
+                        "claimed"
+                    } else {
+                        "released"
+                    },
+                    scheme.handler.as_deref().unwrap_or("no handler"),
+                    if scheme.is_ours {
+                        " (this launcher)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        Some(SchemesCommand::Unregister(_)) => {
+            schemes::unregister_all()?;
+            println!("The launcher no longer handles omp:// and samp:// links");
+        }
+        None => {
+            return Err(LauncherError::InvalidInput(
+                "schemes: a command is required".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The favorites of the SA-MP client if `samp` is set, otherwise the
+/// launcher's.
+fn favorites_of(samp: bool) -> Result<Vec<FavoriteServer>> {
+    if samp {
+        Ok(favorites::samp_favorites())
+    } else {
+        favorites::launcher_favorites()
+    }
+}
+
+fn required_path(file: Option<&str>) -> Result<&Path> {
+    file.map(Path::new)
+        .ok_or_else(|| LauncherError::InvalidInput("A file path is required".to_string()))
+}
+
+fn run_verify(args: &VerifyArgs) -> i32 {
+    let dir = match args
+        .dir
+        .as_ref()
+        .map(PathBuf::from)
+        .map_or_else(samp_dir, Ok)
+    {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}", e);
+            return CLI_EXIT_ERROR;
+        }
+    };
+
+    let full_path = |path: &str| dir.join(path).to_string_lossy().into_owned();
+    let present: Vec<String> = SAMP_RESOURCE_CHECKSUMS
+        .iter()
+        .map(|(path, _)| full_path(path))
+        .filter(|path| Path::new(path).is_file())
+        .collect();
+
+    let checksums = match commands::get_checksum_of_files(present) {
+        Ok(checksums) => checksums,
+        Err(e) => {
+            eprintln!("{}", e);
+            return CLI_EXIT_ERROR;
+        }
+    };
+
+    let report: Vec<VerifiedFile> = SAMP_RESOURCE_CHECKSUMS
+        .iter()
+        .map(|&(path, expected)| {
+            let full_path = full_path(path);
+            let actual = checksums
+                .iter()
+                .filter_map(|entry| entry.rsplit_once('|'))
+                .find(|(file, _)| *file == full_path)
+                .map(|(_, checksum)| checksum.to_string());
+            let status = match &actual {
+                None => FileStatus::Missing,
+                Some(actual) if actual == expected => FileStatus::Ok,
+                Some(_) => FileStatus::Mismatch,
+            };
+            VerifiedFile {
+                path,
+                status,
+                expected,
+                actual,
+            }
+        })
+        .collect();
+
+    let failed = report.iter().filter(|f| f.status != FileStatus::Ok).count();
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("{}", e);
+                return CLI_EXIT_ERROR;
+            }
+        }
+    } else {
+        println!("{}", dir.display());
+        for file in &report {
+            let status = match file.status {
+                FileStatus::Ok => "OK",
+                FileStatus::Mismatch => "MISMATCH",
+                FileStatus::Missing => "MISSING",
+            };
+            println!("  {:<9} {}", status, file.path);
+        }
+        println!(
+            "{} of {} files are valid",
+            report.len() - failed,
+            report.len()
+        );
+    }
+
+    if failed > 0 {
+        CLI_EXIT_VERIFY_FAILED
+    } else {
+        CLI_EXIT_SUCCESS
+    }
+}
+
+fn run_extract(args: &ExtractArgs) -> Result<()> {
+    let samp_dir = samp_dir()?;
+    let archive = args
+        .archive
+        .as_ref()
+        .map_or_else(|| samp_dir.join(SAMP_CLIENTS_ARCHIVE), PathBuf::from);
+    let output = args.output.as_ref().map_or(samp_dir, PathBuf::from);
+
+    println!("Extracting {} to {}", archive.display(), output.display());
+    commands::extract_7z(
+        archive.to_string_lossy().into_owned(),
+        output.to_string_lossy().into_owned(),
+    )?;
+
+    if let Some(gamepath) = &args.gamepath {
+        validation::validate_file_path(gamepath)?;
+        let shared = output.join(SAMP_SHARED_DIR);
+        println!("Copying {} to {}", shared.display(), gamepath);
+        helpers::copy_files(&shared, gamepath)?;
+    }
+    Ok(())
+}
+
+fn exit_code_of(error: &QueryError) -> i32 {
+    match error.kind {
+        QueryErrorKind::Timeout => CLI_EXIT_TIMEOUT,
+        QueryErrorKind::Resolve => CLI_EXIT_RESOLVE,
+        QueryErrorKind::InvalidResponse => CLI_EXIT_BAD_RESPONSE,
+        QueryErrorKind::Network => CLI_EXIT_UNREACHABLE,
+        QueryErrorKind::RateLimited | QueryErrorKind::Internal => CLI_EXIT_ERROR,
+    }
+}
+
+fn error_of<T>(outcome: &Option<QueryOutcome<T>>) -> Option<&QueryError> {
+    match outcome {
+        Some(QueryOutcome::Error(e)) => Some(e),
+        _ => None,
+    }
+}
+
+async fn run_query(args: &QueryArgs) -> i32 {
+    let (Some(host), Some(port)) = (&args.host, args.port) else {
+        eprintln!("query: --host and --port are required");
+        return CLI_EXIT_USAGE;
+    };
+    if let Err(e) = validation::validate_hostname(host).and(validation::validate_port(port)) {
+        eprintln!("query: {}", e);
+        return CLI_EXIT_USAGE;
+    }
+
+    let report = query_report(host, port, args).await;
+    let error = error_of(&report.response.info)
+        .or_else(|| error_of(&report.response.players))
+        .or_else(|| error_of(&report.response.rules));
+    let code = error.map_or(CLI_EXIT_SUCCESS, exit_code_of);
+
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("query: {}", e);
+                return CLI_EXIT_ERROR;
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+
+    code
+}
+
+/// Queries the info section, then the optional ones if the server answered.
+async fn query_report(host: &str, port: i32, args: &QueryArgs) -> QueryReport {
+    let mut report = QueryReport {
+        host: host.to_string(),
+        port,
+        address: None,
+        response: ServerQueryResponse::default(),
+    };
+
+    let q = match Query::new(host, port).await {
+        Ok(q) => q,
+        Err(e) => {
+            report.response.info = Some(QueryOutcome::Error(e.into()));
+            return report;
+        }
+    };
+    report.address = Some(q.target().to_string());
+
+    let budget = || QueryBudget::from_millis(args.timeout);
+    let info: QueryOutcome<_> = q
+        .request(QUERY_TYPE_INFO, &budget())
+        .await
+        .and_then(QueryPacket::into_info)
+        .into();
+    let online = matches!(info, QueryOutcome::Ok(_));
+    report.response.info = Some(info);
+    if !online {
+        return report;
+    }
+
+    let ping_budget = budget();
+    let stats = ping::measure(PING_PROBE_COUNT, || q.ping(&ping_budget)).await;
+    report.response.ping = Some(stats.display_ms());
+    report.response.ping_stats = Some(stats);
+
+    if args.players {
+        report.response.players = Some(q.request_players(&budget()).await.into());
+    }
+
+    if args.rules {
+        report.response.rules = Some(
+            q.request(QUERY_TYPE_RULES, &budget())
+                .await
+                .and_then(QueryPacket::into_rules)
+                .into(),
+        );
+    }
+
+    report
+}
+
+fn print_report(report: &QueryReport) {
+    let response = &report.response;
+    let info = match &response.info {
+        Some(QueryOutcome::Ok(info)) => info,
+        Some(QueryOutcome::Error(e)) => {
+            eprintln!("{}:{}: {}", report.host, report.port, e.message);
+            return;
+        }
+        None => return,
+    };
+
+    println!("{}", info.hostname);
+    println!(
+        "  Address:   {}:{}{}",
+        report.host,
+        report.port,
+        report
+            .address
+            .as_ref()
+            .map(|addr| format!(" ({})", addr))
+            .unwrap_or_default()
+    );
+    println!("  Players:   {}/{}", info.players, info.max_players);
+    println!("  Gamemode:  {}", info.gamemode);
+    println!("  Language:  {}", info.language);
+    println!("  Password:  {}", if info.password { "yes" } else { "no" });
+    if let Some(stats) = &response.ping_stats {
+        print_ping(stats);
+    }
+
+    match &response.players {
+        Some(QueryOutcome::Ok(players)) => {
+            println!("\nPlayers ({}):", players.len());
+            for player in players {
+                let id = player.id.map(|id| id.to_string()).unwrap_or_default();
+                let ping = player.ping.map(|ping| ping.to_string()).unwrap_or_default();
+                println!(
+                    "  {:>3}  {:<24} {:>8} {:>5}",
+                    id, player.name, player.score, ping
+                );
+            }
+        }
+        Some(QueryOutcome::Error(e)) => eprintln!("\nPlayers: {}", e.message),
+        None => {}
+    }
+
+    match &response.rules {
+        Some(QueryOutcome::Ok(rules)) => {
+            println!("\nRules ({}):", rules.len());
+            for (name, value) in rules {
+                println!("  {} = {}", name, value);
+            }
+        }
+        Some(QueryOutcome::Error(e)) => eprintln!("\nRules: {}", e.message),
+        None => {}
+    }
+}
+
+fn print_ping(stats: &PingStats) {
+    if stats.received == 0 {
+        println!("  Ping:      no reply");
+    } else {
+        println!("  Ping:      {} ms ({:.0}% loss)", stats.avg_ms, stats.loss);
+    }
+}
@@ -1,4 +1,14 @@
 pub const IPC_PORT: u16 = 45791;
+pub const IPC_PROTOCOL_VERSION: u32 = 1;
+pub const IPC_TOKEN_ENV: &str = "OMP_LAUNCHER_IPC_TOKEN";
+pub const IPC_TOKEN_BYTES: usize = 32;
+pub const IPC_UNBOUND_TOKEN_TTL_MS: u64 = 10000;
+pub const IPC_PORT_ENV: &str = "OMP_LAUNCHER_IPC_PORT";
+pub const IPC_DISCOVERY_FILE: &str = "ipc_discovery.json";
+pub const IPC_HEARTBEAT_TIMEOUT_MS: u64 = 15000;
+pub const IPC_OUTBOUND_QUEUE_SIZE: usize = 64;
+pub const IPC_FLUSH_TIMEOUT_MS: u64 = 1000;
+pub const IPC_MAX_LINE_LENGTH: usize = 4096;
 
 pub const MAX_HOSTNAME_LENGTH: u32 = 63;
 pub const MAX_GAMEMODE_LENGTH: u32 = 39;
@@ -13,9 +23,29 @@ pub const MAX_RULE_VALUE_LENGTH: u8 = 70;
 pub const MAX_PLAYER_COUNT: u16 = 1000;
 pub const MAX_RULE_COUNT: u16 = 20;
 
-pub const QUERY_TIMEOUT_SECS: u64 = 2;
+pub const QUERY_DEFAULT_BUDGET_MS: u64 = 5000;
+pub const QUERY_MAX_ATTEMPTS: u32 = 4;
+pub const QUERY_INITIAL_RTO_MS: u64 = 600;
+pub const QUERY_MIN_RTO_MS: u64 = 150;
+pub const QUERY_MAX_RTO_MS: u64 = 2000;
+pub const QUERY_RTO_GRANULARITY_MS: u64 = 20;
+pub const DETAILED_PLAYERS_BUDGET_MS: u64 = 1500;
+pub const DETAILED_PLAYERS_RETRY_SECS: u64 = 600;
 pub const QUERY_RATE_LIMIT_MS: u64 = 1000;
+pub const QUERY_CACHE_TTL_SECS: u64 = 60;
 pub const OMP_EXTRA_INFO_UPDATE_COOLDOWN_SECS: u64 = 3;
+pub const BATCH_QUERY_MAX_IN_FLIGHT: usize = 256;
+/// Servers of a batch queried at once. Each sends up to 7 requests, so a full
+/// batch leaves part of `BATCH_QUERY_MAX_IN_FLIGHT` to the server watcher.
+pub const BATCH_QUERY_MAX_SERVERS: usize = 24;
+pub const BATCH_QUERY_MAX_RESOLVING: usize = 16;
+pub const SERVER_WATCH_TICK_MS: u64 = 250;
+pub const SERVER_WATCH_MIN_INTERVAL_MS: u64 = 1000;
+pub const SERVER_WATCH_PING_PROBES: u32 = 1;
+pub const SERVER_WATCH_DEFAULT_INTERVAL_MS: u64 = 10000;
+pub const RCON_REPLY_TIMEOUT_MS: u64 = 3000;
+pub const RCON_QUIET_PERIOD_MS: u64 = 500;
+pub const RCON_MAX_DURATION_MS: u64 = 10000;
 
 pub const INJECTION_MAX_RETRIES: u32 = 5;
 pub const INJECTION_RETRY_DELAY_MS: u64 = 500;
@@ -24,14 +54,59 @@ pub const UDP_BUFFER_SIZE: usize = 1500;
 pub const PROCESS_MODULE_BUFFER_SIZE: usize = 1024;
 
 pub const SAMP_PACKET_HEADER: &[u8] = b"SAMP";
+pub const QUERY_HEADER_LENGTH: usize = 11;
 
 pub const QUERY_TYPE_INFO: char = 'i';
 pub const QUERY_TYPE_PLAYERS: char = 'c';
+pub const QUERY_TYPE_DETAILED_PLAYERS: char = 'd';
 pub const QUERY_TYPE_RULES: char = 'r';
 pub const QUERY_TYPE_EXTRA_INFO: char = 'o';
 pub const QUERY_TYPE_PING: char = 'p';
+pub const QUERY_TYPE_RCON: char = 'x';
+
+pub const RCON_INVALID_PASSWORD_REPLY: &str = "Invalid RCON password.";
 
 pub const PING_TIMEOUT: u32 = 9999;
+pub const PING_PROBE_COUNT: u32 = 3;
+pub const PING_MAX_PROBE_COUNT: u32 = 20;
+pub const PING_PROBE_INTERVAL_MS: u64 = 50;
+pub const PING_PROBE_TIMEOUT_MS: u64 = 1000;
+
+pub const LAN_SCAN_DEFAULT_PORT_FROM: u16 = 7777;
+pub const LAN_SCAN_DEFAULT_PORT_TO: u16 = 7786;
+pub const LAN_SCAN_MAX_PORTS: u16 = 100;
+pub const LAN_SCAN_MIN_PREFIX: u32 = 22;
+pub const LAN_SCAN_PROBES_PER_SEC: u32 = 1000;
+pub const LAN_SCAN_REPLY_WAIT_MS: u64 = 1000;
+pub const LAN_SCAN_MAX_TIMEOUT_MS: u64 = 30000;
+
+pub const MASTER_LIST_BASE_URL: &str = "https://api.open.mp";
+pub const MASTER_LIST_TIMEOUT_SECS: u64 = 30;
+pub const MASTER_LIST_SNAPSHOT_PREFIX: &str = "master_list";
+
+pub const SERVER_HISTORY_FILE: &str = "server_history.json";
+pub const SERVER_HISTORY_RECENT_BUCKET_SECS: u64 = 300;
+pub const SERVER_HISTORY_RECENT_SECS: u64 = 24 * 60 * 60;
+pub const SERVER_HISTORY_OLD_BUCKET_SECS: u64 = 60 * 60;
+pub const SERVER_HISTORY_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+pub const SERVER_HISTORY_MAX_ENDPOINTS: usize = 500;
+pub const SERVER_HISTORY_FLUSH_INTERVAL_SECS: u64 = 60;
+
+pub const CLI_EXIT_SUCCESS: i32 = 0;
+pub const CLI_EXIT_ERROR: i32 = 1;
+pub const CLI_EXIT_USAGE: i32 = 2;
+pub const CLI_EXIT_TIMEOUT: i32 = 3;
+pub const CLI_EXIT_RESOLVE: i32 = 4;
+pub const CLI_EXIT_BAD_RESPONSE: i32 = 5;
+pub const CLI_EXIT_UNREACHABLE: i32 = 6;
+pub const CLI_EXIT_VERIFY_FAILED: i32 = 7;
+pub const CLI_EXIT_HANDOFF_REJECTED: i32 = 8;
+pub const CLI_EXIT_HANDOFF_FAILED: i32 = 9;
+
+pub const FAVORITES_FILE_VERSION: u32 = 1;
+pub const PENDING_FAVORITES_FILE: &str = "pending_favorites.json";
+pub const LAUNCHER_FAVORITES_FILE: &str = "favorites.json";
+pub const LAUNCHER_SETTINGS_FILE: &str = "launcher_settings.json";
 
 pub const LOG_FILE_NAME: &str = "omp-launcher.log";
 pub const DATA_DIR_NAME: &str = "mp.open.launcher";
@@ -40,9 +115,48 @@ pub const GTA_SA_EXECUTABLE: &str = "gta_sa.exe";
 pub const SAMP_DLL: &str = "samp.dll";
 pub const OMP_CLIENT_DLL: &str = "omp-client.dll";
 
+pub const SAMP_RESOURCE_DIR: &str = "samp";
+pub const SAMP_SHARED_DIR: &str = "shared";
+pub const SAMP_CLIENTS_ARCHIVE: &str = "samp_clients.7z";
+
+/// Files of the samp resource directory and their MD5 checksums, the same
+/// list the frontend validates on startup.
+pub const SAMP_RESOURCE_CHECKSUMS: &[(&str, &str)] = &[
+    ("samp_clients.7z", "5572377f1c6f9fbcb673a8cf26c19984"),
+    ("0.3.7-R1/samp.dll", "1d22eaa2605717ddf215f68e861de378"),
+    ("0.3.7-R2/samp.dll", "074241172174f9f2f93afce3261f97ad"),
+    ("0.3.7-R3/samp.dll", "61dfd96e0bb01e2fd8cd27e0df18e653"),
+    ("0.3.7-R3-1/samp.dll", "08cf4166d916e314ed3ee8cff2f13cca"),
+    ("0.3.7-R4/samp.dll", "7b3a5b379848eda9f9e26f633515a77d"),
+    ("0.3.7-R5/samp.dll", "5ba5f0be7af99dfd03fb39e88a970a2b"),
+    ("0.3.DL/samp.dll", "449e4f985215ffb5bffadf23551c0d50"),
+    ("shared/bass.dll", "8f5b9b73d33e8c99202b5058cb6dce51"),
+    ("shared/gtaweap3.ttf", "59cbae9fd42a9a4eea90af7f81e5e734"),
+    ("shared/mouse.png", "337ddcbe53be7dd8032fb8f6fe1b607b"),
+    ("shared/samp.saa", "833af65bc94eea6f8503900ef597ad51"),
+    ("shared/sampaux3.ttf", "6a03a32076e76f6c1720cad6c6ea6915"),
+    ("shared/sampgui.png", "1423c18dfa2064d967b397227960b93d"),
+    (
+        "shared/SAMP/blanktex.txd",
+        "00dc42d499f5ca6059e4683fd761f032",
+    ),
+    ("shared/SAMP/CUSTOM.ide", "d41d8cd98f00b204e9800998ecf8427e"),
+    ("shared/SAMP/custom.img", "8fc7f2ec79402a952d5b896b710b3a41"),
+    ("shared/SAMP/samaps.txd", "e0fdfd9fbe272baa9284e275fb426610"),
+    ("shared/SAMP/SAMP.ide", "9fc8a6769f18d3daceabbbed8632c68e"),
+    ("shared/SAMP/SAMP.img", "c85eb523407583f602a2f48df572081f"),
+    ("shared/SAMP/SAMP.ipl", "f5fc70efa49b43fc48fc71e3c680b50e"),
+    (
+        "shared/SAMP/SAMPCOL.img",
+        "eb690e98b644fa584be6917d48ee6cbc",
+    ),
+];
+
 pub const DEEPLINK_SCHEME_OMP: &str = "omp";
 pub const DEEPLINK_SCHEME_SAMP: &str = "samp";
 pub const DEEPLINK_IDENTIFIER: &str = "mp.open.launcher";
+pub const DEFAULT_SERVER_PORT: u16 = 7777;
+pub const LINK_COOLDOWN_MS: u64 = 2000;
 
 pub const WINDOW_MIN_WIDTH: u32 = 1000;
 pub const WINDOW_MIN_HEIGHT: u32 = 700;
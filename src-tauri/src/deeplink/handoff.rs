@@ -0,0 +1,155 @@
+use std::{
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write},
+    sync::mpsc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the messages exchanged between instances. A primary instance
+/// rejects messages of a newer version than it knows.
+pub const HANDOFF_VERSION: u32 = 1;
+
+/// How long a secondary instance waits for the primary to answer.
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a secondary instance asks the primary instance to do.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Intent {
+    /// Open a link, e.g. `omp://127.0.0.1:7777`.
+    OpenUri { uri: String },
+    /// Launch the game with the options in `argv`.
+    Launch,
+    /// Bring the primary instance to the front.
+    Focus,
+}
+
+/// The message a secondary instance sends, one JSON object per line.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Handoff {
+    pub version: u32,
+    /// Every argument of the secondary instance, including the program.
+    pub argv: Vec<String>,
+    /// Working directory of the secondary instance, relative paths in `argv`
+    /// are relative to it.
+    pub cwd: Option<String>,
+    pub intent: Intent,
+}
+
+impl Handoff {
+    pub fn new(intent: Intent) -> Self {
+        Self {
+            version: HANDOFF_VERSION,
+            argv: std::env::args().collect(),
+            cwd: std::env::current_dir()
+                .ok()
+                .map(|dir| dir.display().to_string()),
+            intent,
+        }
+    }
+
+    /// Parses a line sent by a secondary instance. Versions before the JSON
+    /// format sent only their first argument.
+    fn parse(line: &str) -> std::result::Result<Self, String> {
+        if !line.trim_start().starts_with('{') {
+            let arg = line.to_string();
+            return Ok(Self {
+                version: 0,
+                argv: vec![String::new(), arg.clone()],
+                cwd: None,
+                intent: if arg.contains("://") {
+                    Intent::OpenUri { uri: arg }
+                } else {
+                    Intent::Focus
+                },
+            });
+        }
+
+        let handoff: Handoff =
+            serde_json::from_str(line).map_err(|e| format!("Invalid message: {}", e))?;
+        if handoff.version > HANDOFF_VERSION {
+            return Err(format!(
+                "Message version {} is not supported, expected at most {}",
+                handoff.version, HANDOFF_VERSION
+            ));
+        }
+        Ok(handoff)
+    }
+}
+
+/// The primary instance's answer to a [`Handoff`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ack {
+    pub version: u32,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Sends `handoff` over `conn` and waits for the primary instance's [`Ack`].
+pub(super) fn send<S: Read + Write + Send + 'static>(
+    mut conn: S,
+    handoff: &Handoff,
+) -> Result<Ack> {
+    let mut line = serde_json::to_string(handoff)?;
+    line.push('\n');
+    conn.write_all(line.as_bytes())?;
+    conn.flush()?;
+
+    // the sockets have no portable read timeout, the reader is abandoned if
+    // the primary instance never answers
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reply = String::new();
+        let _ = tx.send(BufReader::new(conn).read_line(&mut reply).map(|_| reply));
+    });
+
+    let reply = rx
+        .recv_timeout(ACK_TIMEOUT)
+        .map_err(|_| Error::new(ErrorKind::TimedOut, "The primary instance did not answer"))??;
+    if reply.is_empty() {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "The primary instance closed the connection",
+        ));
+    }
+    serde_json::from_str(&reply).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Reads one [`Handoff`] from `conn`, passes it to `handler` and answers with
+/// the outcome.
+pub(super) fn serve<S, F>(conn: S, handler: F)
+where
+    S: Read + Write,
+    F: FnOnce(Handoff) -> std::result::Result<(), String>,
+{
+    let mut conn = BufReader::new(conn);
+    let mut line = String::new();
+    match conn.read_line(&mut line) {
+        // a connection that only checked for a primary instance
+        Ok(0) => return,
+        Ok(_) => {}
+        Err(io_err) => {
+            log::error!("Error reading incoming connection: {}", io_err);
+            return;
+        }
+    }
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    let result = Handoff::parse(line).and_then(handler);
+    if let Err(e) = &result {
+        log::warn!("Rejected message from another instance: {}", e);
+    }
+
+    let ack = Ack {
+        version: HANDOFF_VERSION,
+        ok: result.is_ok(),
+        error: result.err(),
+    };
+    // secondaries from before the JSON format do not wait for an answer
+    if let Ok(mut reply) = serde_json::to_string(&ack) {
+        reply.push('\n');
+        let _ = conn.get_mut().write_all(reply.as_bytes());
+    }
+}
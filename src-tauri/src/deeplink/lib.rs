@@ -1,4 +1,5 @@
 use std::io::{/*ErrorKind, */ Result};
+use std::path::PathBuf;
 
 use once_cell::sync::OnceCell;
 
@@ -6,6 +7,15 @@ use once_cell::sync::OnceCell;
 #[path = "windows.rs"]
 mod platform_impl;
 
+#[cfg(target_os = "linux")]
+#[path = "linux.rs"]
+mod platform_impl;
+
+#[path = "handoff.rs"]
+mod handoff;
+
+pub use handoff::{Ack, Handoff, Intent, HANDOFF_VERSION};
+
 static ID: OnceCell<String> = OnceCell::new();
 
 /// This function is meant for use-cases where the default [`prepare()`] function can't be used.
@@ -17,41 +27,71 @@ static ID: OnceCell<String> = OnceCell::new();
 //         .map_err(|_| ErrorKind::AlreadyExists.into())
 // }
 
-// Consider adding a function to register without starting the listener.
-
-/// Registers a handler for the given scheme.
+/// Registers a handler for the given scheme. The handler gets what other
+/// instances hand off, its error is sent back to them.
 ///
 /// ## Platform-specific:
 ///
 /// - **macOS**: On macOS schemes must be defined in an Info.plist file, therefore this function only calls [`listen()`] without registering the scheme. This function can only be called once on macOS.
-pub fn register<F: FnMut(String) + Send + 'static>(scheme: &str, handler: F) -> Result<()> {
+/// - **Linux**: Writes a `.desktop` handler to the XDG data directory and makes it the default for the scheme with `xdg-mime`. All schemes share one socket in `$XDG_RUNTIME_DIR`.
+pub fn register<F>(scheme: &str, handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
     platform_impl::register(scheme, handler)
 }
 
+/// Makes this executable the handler of the scheme without starting the listener.
+pub fn claim(scheme: &str) -> Result<()> {
+    platform_impl::claim(scheme)
+}
+
 /// Starts the event listener without registering any schemes.
 ///
 /// ## Platform-specific:
 ///
 /// - **macOS**: This function can only be called once on macOS.
-// pub fn listen<F: FnMut(String) + Send + 'static>(handler: F) -> Result<()> {
-//     platform_impl::listen(handler)
-// }
+pub fn listen<F>(handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
+    platform_impl::listen(handler)
+}
 
 /// Unregister a previously registered scheme.
 ///
 /// ## Platform-specific:
 ///
 /// - **macOS**: This function has no effect on macOS.
-// pub fn unregister(scheme: &str) -> Result<()> {
-//     platform_impl::unregister(scheme)
-// }
+/// - **Windows**: Keys that point to another executable are left alone.
+/// - **Linux**: Removes the `.desktop` handler and its associations in `mimeapps.list`.
+pub fn unregister(scheme: &str) -> Result<()> {
+    platform_impl::unregister(scheme)
+}
+
+/// The executable the system currently opens links of the scheme with, if any.
+pub fn handler(scheme: &str) -> Result<Option<PathBuf>> {
+    platform_impl::handler(scheme)
+}
 
-/// Checks if current instance is the primary instance.
-/// Also sends the URL event data to the primary instance and stops the process afterwards.
+/// The executable [`register()`] registers for a scheme.
+pub fn executable() -> Result<PathBuf> {
+    platform_impl::executable()
+}
+
+/// Sets the identifier the instances find each other by. Must be called
+/// before any other function.
 ///
 /// ## Platform-specific:
 ///
 /// - **macOS**: Only registers the identifier (only relevant in debug mode). It does not interact with the primary instance and does not exit the app.
+/// - **Linux**: Removes the socket of a primary instance that did not exit cleanly.
 pub fn prepare(identifier: &str) {
     platform_impl::prepare(identifier)
 }
+
+/// Hands `intent` off to the primary instance, if there is one, and returns
+/// its answer. `None` means this is the primary instance.
+pub fn forward(intent: Intent) -> Option<Result<Ack>> {
+    platform_impl::forward(&Handoff::new(intent))
+}
@@ -0,0 +1,321 @@
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+use once_cell::sync::OnceCell;
+
+use super::{handoff, Ack, Handoff, Intent, ID};
+
+type Handler = Box<dyn FnMut(Handoff) -> std::result::Result<(), String> + Send>;
+
+/// Handlers by scheme. There is a single socket for all of them, unlike the
+/// named pipes on Windows which can be bound once per scheme.
+static HANDLERS: Mutex<Vec<(String, Handler)>> = Mutex::new(Vec::new());
+static LISTENER: OnceCell<()> = OnceCell::new();
+
+pub fn register<F>(scheme: &str, handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
+    add_handler(scheme, Box::new(handler));
+    claim(scheme)
+}
+
+pub fn claim(scheme: &str) -> Result<()> {
+    let applications = applications_dir()?;
+    fs::create_dir_all(&applications)?;
+    let exe = executable()?;
+
+    let file_name = desktop_file_name(scheme);
+    fs::write(
+        applications.join(&file_name),
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Exec={} %u\n\
+             Terminal=false\n\
+             NoDisplay=true\n\
+             MimeType=x-scheme-handler/{};\n",
+            ID.get().expect("register() called before prepare()"),
+            exec_quote(&exe.display().to_string()),
+            scheme
+        ),
+    )?;
+
+    // a desktop without xdg-utils can still pick the handler up from the
+    // desktop file, so failing here is not fatal
+    if let Err(e) = run_tool(
+        Command::new("xdg-mime")
+            .arg("default")
+            .arg(&file_name)
+            .arg(format!("x-scheme-handler/{}", scheme)),
+    ) {
+        log::warn!("Failed to set the default {}:// handler: {}", scheme, e);
+    }
+    update_desktop_database(&applications);
+
+    Ok(())
+}
+
+pub fn listen<F>(handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
+    add_handler("", Box::new(handler));
+    Ok(())
+}
+
+fn add_handler(scheme: &str, handler: Handler) {
+    match HANDLERS.lock() {
+        Ok(mut handlers) => handlers.push((scheme.to_lowercase(), handler)),
+        Err(poisoned) => poisoned.into_inner().push((scheme.to_lowercase(), handler)),
+    }
+    if LISTENER.set(()).is_ok() {
+        // as on Windows, a second instance that could not take over the
+        // socket still runs, it just does not receive links
+        if let Err(e) = listen_socket() {
+            log::error!("Can't create listener: {}", e);
+        }
+    }
+}
+
+pub fn unregister(scheme: &str) -> Result<()> {
+    let file_name = desktop_file_name(scheme);
+    let applications = applications_dir()?;
+    match fs::remove_file(applications.join(&file_name)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    // xdg-mime has no way to unset a default, drop our entry from the lists
+    if let Some(mimeapps) = dirs_next::config_dir().map(|dir| dir.join("mimeapps.list")) {
+        match fs::read_to_string(&mimeapps) {
+            Ok(content) => {
+                let key = format!("x-scheme-handler/{}=", scheme);
+                let cleaned: Vec<String> = content
+                    .lines()
+                    .filter_map(|line| match line.strip_prefix(&key) {
+                        Some(ids) => {
+                            let ids: Vec<&str> = ids
+                                .split(';')
+                                .filter(|id| !id.is_empty() && *id != file_name)
+                                .collect();
+                            (!ids.is_empty()).then(|| format!("{}{};", key, ids.join(";")))
+                        }
+                        None => Some(line.to_string()),
+                    })
+                    .collect();
+                fs::write(&mimeapps, cleaned.join("\n") + "\n")?;
+            }
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            Err(_) => {}
+        }
+    }
+
+    update_desktop_database(&applications);
+    Ok(())
+}
+
+pub fn handler(scheme: &str) -> Result<Option<PathBuf>> {
+    let output = match Command::new("xdg-mime")
+        .arg("query")
+        .arg("default")
+        .arg(format!("x-scheme-handler/{}", scheme))
+        .output()
+    {
+        Ok(output) => output,
+        // without xdg-utils there is no telling
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let desktop_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if desktop_id.is_empty() {
+        return Ok(None);
+    }
+
+    let Some(entry) = data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications").join(&desktop_id))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    Ok(fs::read_to_string(entry)?
+        .lines()
+        .find_map(|line| line.strip_prefix("Exec="))
+        .and_then(exec_program)
+        .map(PathBuf::from))
+}
+
+pub fn executable() -> Result<PathBuf> {
+    match std::env::var_os("APPIMAGE") {
+        // the executable of an AppImage lives in a mount that is gone once it exits
+        Some(appimage) => Ok(PathBuf::from(appimage)),
+        None => tauri_utils::platform::current_exe(),
+    }
+}
+
+fn applications_dir() -> Result<PathBuf> {
+    dirs_next::data_dir()
+        .map(|dir| dir.join("applications"))
+        .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "No XDG data directory"))
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, in lookup order.
+fn data_dirs() -> Vec<PathBuf> {
+    let system = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs_next::data_dir()
+        .into_iter()
+        .chain(system.split(':').map(PathBuf::from))
+        .collect()
+}
+
+fn update_desktop_database(applications: &Path) {
+    if let Err(e) = run_tool(Command::new("update-desktop-database").arg(applications)) {
+        log::warn!("Failed to update the desktop database: {}", e);
+    }
+}
+
+fn desktop_file_name(scheme: &str) -> String {
+    format!(
+        "{}-{}.desktop",
+        ID.get().expect("register() called before prepare()"),
+        scheme
+    )
+}
+
+/// Quotes `arg` for the `Exec` key of a desktop entry, which unescapes it
+/// once as a string value and once as an argument.
+fn exec_quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' => {
+                quoted.push_str(r"\\");
+                quoted.push(c);
+            }
+            '\\' => quoted.push_str(r"\\\\"),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The program of an `Exec` value, undoing [`exec_quote()`].
+fn exec_program(exec: &str) -> Option<String> {
+    let exec = exec.trim().replace(r"\\", "\\");
+    let program = match exec.strip_prefix('"') {
+        Some(quoted) => {
+            let mut program = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => program.extend(chars.next()),
+                    c => program.push(c),
+                }
+            }
+            program
+        }
+        None => exec.split_whitespace().next()?.to_string(),
+    };
+    Some(program.replace("%%", "%")).filter(|program| !program.is_empty())
+}
+
+fn run_tool(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("exited with {}", status)))
+    }
+}
+
+/// The socket the primary instance listens on. `$XDG_RUNTIME_DIR` is only
+/// accessible by the user, the temp dir fallback is shared.
+fn socket_path(identifier: &str) -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join(format!("{}.sock", identifier)),
+        None => std::env::temp_dir().join(format!(
+            "{}-{}.sock",
+            identifier,
+            std::env::var("USER").unwrap_or_default()
+        )),
+    }
+}
+
+fn listen_socket() -> Result<()> {
+    let path = socket_path(ID.get().expect("listen() called before prepare()"));
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+    std::thread::spawn(move || {
+        for conn in listener.incoming().filter_map(|c| {
+            c.map_err(|error| log::error!("Incoming connection failed: {}", error))
+                .ok()
+        }) {
+            handoff::serve(conn, dispatch);
+        }
+    });
+
+    Ok(())
+}
+
+/// Passes `message` to the handler of the scheme of the link it opens, or to
+/// the first handler, as the Windows listener would.
+fn dispatch(message: Handoff) -> std::result::Result<(), String> {
+    let scheme = match &message.intent {
+        Intent::OpenUri { uri } => uri
+            .split_once("://")
+            .map(|(scheme, _)| scheme.to_lowercase())
+            .unwrap_or_default(),
+        _ => String::new(),
+    };
+
+    let mut handlers = match HANDLERS.lock() {
+        Ok(handlers) => handlers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let index = handlers
+        .iter()
+        .position(|(registered, _)| *registered == scheme)
+        .unwrap_or(0);
+    match handlers.get_mut(index) {
+        Some((_, handler)) => handler(message),
+        None => Err("No handler is registered".to_string()),
+    }
+}
+
+pub fn prepare(identifier: &str) {
+    let path = socket_path(identifier);
+    // left behind by a primary instance that did not exit cleanly
+    if let Err(e) = UnixStream::connect(&path) {
+        if e.kind() == ErrorKind::ConnectionRefused {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    ID.set(identifier.to_string())
+        .expect("prepare() called more than once with different identifiers.");
+}
+
+pub fn forward(message: &Handoff) -> Option<Result<Ack>> {
+    let path = socket_path(ID.get().expect("forward() called before prepare()"));
+    // We are the secondary instance.
+    let conn = UnixStream::connect(path).ok()?;
+    Some(handoff::send(conn, message))
+}
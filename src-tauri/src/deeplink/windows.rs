@@ -1,6 +1,6 @@
 use std::{
-    io::{BufRead, BufReader, Result, Write},
-    path::Path,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
 };
 
 use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
@@ -8,20 +8,26 @@ use windows_sys::Win32::UI::{
     Input::KeyboardAndMouse::{SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT},
     WindowsAndMessaging::{AllowSetForegroundWindow, ASFW_ANY},
 };
-use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+use winreg::{
+    enums::{HKEY_CLASSES_ROOT, HKEY_CURRENT_USER},
+    RegKey,
+};
 
-use super::ID;
+use super::{handoff, Ack, Handoff, ID};
 
-pub fn register<F: FnMut(String) + Send + 'static>(scheme: &str, handler: F) -> Result<()> {
+pub fn register<F>(scheme: &str, handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
     listen(handler)?;
+    claim(scheme)
+}
 
+pub fn claim(scheme: &str) -> Result<()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let base = Path::new("Software").join("Classes").join(scheme);
 
-    let exe = tauri_utils::platform::current_exe()?
-        .display()
-        .to_string()
-        .replace("\\\\?\\", "");
+    let exe = executable()?.display().to_string();
 
     let (key, _) = hkcu.create_subkey(&base)?;
     key.set_value(
@@ -43,16 +49,71 @@ pub fn register<F: FnMut(String) + Send + 'static>(scheme: &str, handler: F) ->
     Ok(())
 }
 
-// pub fn unregister(scheme: &str) -> Result<()> {
-//     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-//     let base = Path::new("Software").join("Classes").join(scheme);
+/// Removes the scheme's keys if they point to this executable, another
+/// client may have taken the scheme over since.
+pub fn unregister(scheme: &str) -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let base = Path::new("Software").join("Classes").join(scheme);
 
-//     hkcu.delete_subkey_all(base)?;
+    let command: String = match hkcu
+        .open_subkey(base.join("shell").join("open").join("command"))
+        .and_then(|key| key.get_value(""))
+    {
+        Ok(command) => command,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let exe = executable()?.display().to_string();
+    if !command_executable(&command).is_some_and(|registered| registered.eq_ignore_ascii_case(&exe))
+    {
+        return Ok(());
+    }
+
+    hkcu.delete_subkey_all(base)
+}
+
+pub fn handler(scheme: &str) -> Result<Option<PathBuf>> {
+    // HKEY_CLASSES_ROOT merges the user's classes over the machine's
+    let hkcr = RegKey::predef(HKEY_CLASSES_ROOT);
+    let command: String = match hkcr
+        .open_subkey(Path::new(scheme).join("shell").join("open").join("command"))
+        .and_then(|key| key.get_value(""))
+    {
+        Ok(command) => command,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    Ok(command_executable(&command).map(PathBuf::from))
+}
+
+/// The executable of a `shell\open\command` value, which is either quoted or,
+/// as [`register()`] writes it, followed by `"%1"`.
+fn command_executable(command: &str) -> Option<&str> {
+    let command = command.trim();
+    let exe = match command.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => match command.to_ascii_lowercase().find(".exe") {
+            Some(end) => Some(&command[..end + 4]),
+            None => command.split_whitespace().next(),
+        },
+    };
+    exe.filter(|exe| !exe.is_empty())
+}
 
-//     Ok(())
-// }
+pub fn executable() -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        tauri_utils::platform::current_exe()?
+            .display()
+            .to_string()
+            .replace("\\\\?\\", ""),
+    ))
+}
 
-pub fn listen<F: FnMut(String) + Send + 'static>(mut handler: F) -> Result<()> {
+pub fn listen<F>(mut handler: F) -> Result<()>
+where
+    F: FnMut(Handoff) -> std::result::Result<(), String> + Send + 'static,
+{
     std::thread::spawn(move || {
         let listener =
             LocalSocketListener::bind(ID.get().expect("listen() called before prepare()").as_str())
@@ -62,15 +123,7 @@ pub fn listen<F: FnMut(String) + Send + 'static>(mut handler: F) -> Result<()> {
             c.map_err(|error| log::error!("Incoming connection failed: {}", error))
                 .ok()
         }) {
-            // Listen for the launch arguments
-            let mut conn = BufReader::new(conn);
-            let mut buffer = String::new();
-            if let Err(io_err) = conn.read_line(&mut buffer) {
-                log::error!("Error reading incoming connection: {}", io_err.to_string());
-            };
-            buffer.pop();
-
-            handler(buffer);
+            handoff::serve(conn, &mut handler);
         }
     });
 
@@ -78,35 +131,36 @@ pub fn listen<F: FnMut(String) + Send + 'static>(mut handler: F) -> Result<()> {
 }
 
 pub fn prepare(identifier: &str) {
-    let arg1 = std::env::args().nth(1).unwrap_or_default();
-    if let Ok(mut conn) = LocalSocketStream::connect(identifier) {
-        // We are the secondary instance.
-        // Prep to activate primary instance by allowing another process to take focus.
-
-        // A workaround to allow AllowSetForegroundWindow to succeed - press a key.
-        // This was originally used by Chromium: https://bugs.chromium.org/p/chromium/issues/detail?id=837796
-        dummy_keypress();
-
-        let primary_instance_pid = conn.peer_pid().unwrap_or(ASFW_ANY);
-        unsafe {
-            let success = AllowSetForegroundWindow(primary_instance_pid) != 0;
-            if !success {
-                log::warn!("AllowSetForegroundWindow failed.");
-            }
-        }
-
-        if let Err(io_err) = conn.write_all(arg1.as_bytes()) {
-            log::error!(
-                "Error sending message to primary instance: {}",
-                io_err.to_string()
-            );
-        };
-        let _ = conn.write_all(b"\n");
-    };
     ID.set(identifier.to_string())
         .expect("prepare() called more than once with different identifiers.");
 }
 
+pub fn forward(message: &Handoff) -> Option<Result<Ack>> {
+    let conn = LocalSocketStream::connect(
+        ID.get()
+            .expect("forward() called before prepare()")
+            .as_str(),
+    )
+    .ok()?;
+
+    // We are the secondary instance.
+    // Prep to activate primary instance by allowing another process to take focus.
+
+    // A workaround to allow AllowSetForegroundWindow to succeed - press a key.
+    // This was originally used by Chromium: https://bugs.chromium.org/p/chromium/issues/detail?id=837796
+    dummy_keypress();
+
+    let primary_instance_pid = conn.peer_pid().unwrap_or(ASFW_ANY);
+    unsafe {
+        let success = AllowSetForegroundWindow(primary_instance_pid) != 0;
+        if !success {
+            log::warn!("AllowSetForegroundWindow failed.");
+        }
+    }
+
+    Some(handoff::send(conn, message))
+}
+
 /// Send a dummy keypress event so AllowSetForegroundWindow can succeed
 fn dummy_keypress() {
     let keyboard_input_down = KEYBDINPUT {
@@ -7,6 +7,7 @@ pub enum LauncherError {
     SystemTime(std::time::SystemTimeError),
     Parse(String),
     Network(String),
+    Timeout(String),
     Process(String),
     Injection(String),
     Registry(String),
@@ -25,6 +26,7 @@ impl fmt::Display for LauncherError {
             LauncherError::SystemTime(err) => write!(f, "System time error: {}", err),
             LauncherError::Parse(msg) => write!(f, "Parse error: {}", msg),
             LauncherError::Network(msg) => write!(f, "Network error: {}", msg),
+            LauncherError::Timeout(msg) => write!(f, "Timed out: {}", msg),
             LauncherError::Process(msg) => write!(f, "Process error: {}", msg),
             LauncherError::Injection(msg) => write!(f, "Injection error: {}", msg),
             LauncherError::Registry(msg) => write!(f, "Registry error: {}", msg),
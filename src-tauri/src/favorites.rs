@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{constants::*, errors::*, helpers, samp, validation};
+
+/// A server in the favorites file the launcher exports and imports.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FavoriteServer {
+    pub ip: String,
+    pub port: i32,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FavoritesFile {
+    pub version: u32,
+    pub servers: Vec<FavoriteServer>,
+}
+
+impl FavoritesFile {
+    pub fn new(servers: Vec<FavoriteServer>) -> Self {
+        Self {
+            version: FAVORITES_FILE_VERSION,
+            servers,
+        }
+    }
+
+    /// Reads a favorites file, dropping servers with an unusable address.
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        let mut file: FavoritesFile = serde_json::from_slice(&data)
+            .map_err(|e| LauncherError::Parse(format!("Invalid favorites file: {}", e)))?;
+
+        if file.version > FAVORITES_FILE_VERSION {
+            return Err(LauncherError::Parse(format!(
+                "Favorites file version {} is not supported",
+                file.version
+            )));
+        }
+
+        file.servers.retain(|server| {
+            validation::validate_hostname(&server.ip).is_ok()
+                && validation::validate_port(server.port).is_ok()
+        });
+        Ok(file)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The favorites of the original SA-MP client, empty if it is not installed.
+pub fn samp_favorites() -> Vec<FavoriteServer> {
+    let data = samp::get_samp_favorite_list();
+    let Ok(user_data) = serde_json::from_str::<samp::SAMPUserData>(&data) else {
+        return Vec::new();
+    };
+
+    user_data
+        .favorite_servers
+        .into_iter()
+        .filter(|server| !server.ip.is_empty())
+        .map(|server| FavoriteServer {
+            ip: server.ip,
+            port: server.port as i32,
+            name: server.name,
+            password: server.password,
+        })
+        .collect()
+}
+
+fn pending_path() -> Result<PathBuf> {
+    Ok(helpers::local_data_dir()?.join(PENDING_FAVORITES_FILE))
+}
+
+fn launcher_path() -> Result<PathBuf> {
+    Ok(helpers::local_data_dir()?.join(LAUNCHER_FAVORITES_FILE))
+}
+
+/// The launcher's favorites as of the last time its window synced them,
+/// followed by the servers `favorites import` queued since.
+pub fn launcher_favorites() -> Result<Vec<FavoriteServer>> {
+    let mut servers = Vec::new();
+    for path in [launcher_path()?, pending_path()?] {
+        if path.exists() {
+            servers.extend(FavoritesFile::read(&path)?.servers);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    servers.retain(|server| seen.insert((server.ip.to_lowercase(), server.port)));
+    Ok(servers)
+}
+
+/// Stores a copy of the favorites, which live in the frontend's storage, for
+/// the `favorites` command line commands.
+#[tauri::command]
+pub fn sync_favorites(servers: Vec<FavoriteServer>) -> Result<()> {
+    let path = launcher_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    FavoritesFile::new(servers).write(&path)
+}
+
+/// Queues `servers` to be added to the favorites the next time the launcher
+/// window opens, the favorites themselves live in the frontend's storage.
+pub fn queue_import(servers: Vec<FavoriteServer>) -> Result<()> {
+    let path = pending_path()?;
+    let mut pending = if path.exists() {
+        FavoritesFile::read(&path)?
+    } else {
+        FavoritesFile::new(Vec::new())
+    };
+    pending.servers.extend(servers);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    pending.write(&path)
+}
+
+/// Returns the servers queued by `favorites import` and clears the queue.
+#[tauri::command]
+pub fn take_pending_favorites() -> Result<Vec<FavoriteServer>> {
+    let path = pending_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let pending = FavoritesFile::read(&path);
+    fs::remove_file(&path)?;
+    Ok(pending?.servers)
+}
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use chardet::{charset2encoding, detect};
 use chardetng::EncodingDetector;
@@ -81,6 +81,17 @@ pub fn decode_buffer(buf: Vec<u8>) -> (String, String) {
     (buff_output, actual_encoding.name().to_string())
 }
 
+/// The launcher's local data directory, where the frontend keeps the `samp`
+/// resources and the omp client. Everything else the launcher stores goes
+/// here too, the command line has no Tauri config to find another one.
+pub fn local_data_dir() -> crate::errors::Result<PathBuf> {
+    dirs_next::data_local_dir()
+        .map(|dir| dir.join(DATA_DIR_NAME))
+        .ok_or_else(|| {
+            crate::errors::LauncherError::InternalError("Failed to get data directory".to_string())
+        })
+}
+
 // resolves a hostname to its first ipv4 address
 pub fn resolve_hostname_to_ipv4(hostname: &str) -> std::result::Result<String, String> {
     use std::net::{IpAddr, ToSocketAddrs};
@@ -67,20 +67,35 @@ pub async fn run_samp(
         ready_for_exec = ready_for_exec.arg("-z").arg(password);
     }
 
+    // the injected client presents the token when connecting to the
+    // launcher's IPC, on the port it is given here
+    let token = crate::ipc::issue_session_token()?;
+    ready_for_exec = ready_for_exec.env(IPC_TOKEN_ENV, &token);
+    if let Some(port) = crate::ipc::port() {
+        ready_for_exec = ready_for_exec.env(IPC_PORT_ENV, port.to_string());
+    }
+
     let process = ready_for_exec.current_dir(executable_dir).spawn();
 
     match process {
         Ok(p) => {
-            inject_dll(p.id(), dll_path, 0, false)?;
-            info!("[run_samp] omp_file.is_empty(): {}", omp_file.is_empty());
-            if !omp_file.is_empty() {
-                inject_dll(p.id(), omp_file, 0, false)
-            } else {
-                Ok(())
+            crate::ipc::bind_session_token(&token, p.id() as i32);
+            let injected = inject_dll(p.id(), dll_path, 0, false).and_then(|_| {
+                info!("[run_samp] omp_file.is_empty(): {}", omp_file.is_empty());
+                if !omp_file.is_empty() {
+                    inject_dll(p.id(), omp_file, 0, false)
+                } else {
+                    Ok(())
+                }
+            });
+            if injected.is_err() {
+                crate::ipc::revoke_session_token(&token);
             }
+            injected
         }
         Err(e) => {
             info!("[injector.rs] Process creation failed: {}", e);
+            crate::ipc::revoke_session_token(&token);
 
             match e.raw_os_error() {
                 Some(ERROR_ELEVATION_REQUIRED) => Err(LauncherError::AccessDenied(
@@ -1,20 +1,112 @@
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::BufRead;
-use std::net::{TcpListener, TcpStream};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::fs;
+use std::net::TcpListener as StdTcpListener;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
-use std::thread;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager, WindowBuilder, WindowUrl};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::timeout;
 
 use crate::constants::*;
+use crate::ipc_protocol::{self, Encoding, GameMessage, LauncherMessage, ProtocolError};
 
-type SharedStreams = Arc<Mutex<HashMap<i32, TcpStream>>>;
+/// The connection of a game and the encoding it speaks.
+pub struct GameStream {
+    /// Lines waiting to be written by the connection's task, bounded so a
+    /// game that stops reading can't pile them up.
+    outbound: mpsc::Sender<String>,
+    pub encoding: Encoding,
+    /// Tells a reconnected game's new connection from the one it replaced.
+    connection_id: u64,
+}
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone)]
+struct GameConnected {
+    pid: i32,
+    /// The negotiated protocol version, 0 for legacy clients.
+    version: u32,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+enum DisconnectReason {
+    Closed,
+    TimedOut,
+    Error,
+}
+
+#[derive(Serialize, Clone)]
+struct GameDisconnected {
+    pid: i32,
+    reason: DisconnectReason,
+}
+
+/// What the task of a connection knows about it.
+struct Connection {
+    id: u64,
+    /// The game's process, known after an authenticated hello.
+    pid: Option<i32>,
+    encoding: Encoding,
+    outbound: mpsc::Sender<String>,
+}
+
+type SharedStreams = Arc<Mutex<HashMap<i32, GameStream>>>;
 
 pub static GAME_STREAMS: Lazy<SharedStreams> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+/// A session token issued for a game launch.
+struct Session {
+    /// The process the token was given to, `None` until it is spawned.
+    pid: Option<i32>,
+    issued_at: Instant,
+}
+
+impl Session {
+    /// A token not yet tied to a process is usable for
+    /// `IPC_UNBOUND_TOKEN_TTL_MS`, a tied one while its process runs.
+    fn is_live(&self) -> bool {
+        match self.pid {
+            Some(pid) => crate::commands::is_process_alive(pid as u32),
+            None => self.issued_at.elapsed() < Duration::from_millis(IPC_UNBOUND_TOKEN_TTL_MS),
+        }
+    }
+}
+
+/// Session tokens of launched games.
+static SESSION_TOKENS: Lazy<Mutex<HashMap<String, Session>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 static IPC_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 static IPC_STARTED: AtomicBool = AtomicBool::new(false);
+/// The port the listener got, `IPC_PORT` unless it was taken.
+static IPC_LISTEN_PORT: OnceLock<u16> = OnceLock::new();
+
+/// The contents of `IPC_DISCOVERY_FILE`, for game clients that were not given
+/// the port and token in their environment.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Discovery {
+    protocol_version: u32,
+    port: u16,
+    /// The launcher process, a file left behind by one that is gone is stale.
+    launcher_pid: u32,
+    sessions: Vec<DiscoverySession>,
+}
+
+#[derive(Serialize)]
+struct DiscoverySession {
+    /// `None` while the game is being spawned.
+    pid: Option<i32>,
+    token: String,
+}
 
 pub fn init_ipc(app_handle: AppHandle) {
     let _ = IPC_HANDLE.set(app_handle);
@@ -32,6 +124,149 @@ pub fn ensure_listening() {
     }
 }
 
+fn session_tokens() -> std::sync::MutexGuard<'static, HashMap<String, Session>> {
+    match SESSION_TOKENS.lock() {
+        Ok(tokens) => tokens,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Creates the token a game about to be launched has to present in its
+/// handshake. Pass it to the game in `IPC_TOKEN_ENV`, then call
+/// [`bind_session_token()`] once the process is spawned.
+pub fn issue_session_token() -> crate::errors::Result<String> {
+    let mut bytes = [0u8; IPC_TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(|e| {
+        crate::errors::LauncherError::InternalError(format!(
+            "Failed to generate IPC session token: {}",
+            e
+        ))
+    })?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    session_tokens().insert(
+        token.clone(),
+        Session {
+            pid: None,
+            issued_at: Instant::now(),
+        },
+    );
+    publish_discovery();
+    Ok(token)
+}
+
+/// Ties `token` to the game process it was given to, only that process can
+/// connect with it.
+pub fn bind_session_token(token: &str, pid: i32) {
+    if let Some(session) = session_tokens().get_mut(token) {
+        session.pid = Some(pid);
+    }
+    publish_discovery();
+}
+
+/// Forgets `token`, e.g. when the game it was issued for failed to launch.
+pub fn revoke_session_token(token: &str) {
+    session_tokens().remove(token);
+    publish_discovery();
+}
+
+/// Forgets the tokens given to `pid`, once its game has disconnected.
+fn revoke_session_tokens_of(pid: i32) {
+    session_tokens().retain(|_, session| session.pid != Some(pid));
+    publish_discovery();
+}
+
+/// The port game clients connect to, `None` until the listener is bound.
+pub fn port() -> Option<u16> {
+    IPC_LISTEN_PORT.get().copied()
+}
+
+/// Writes the port and the session tokens to `IPC_DISCOVERY_FILE` in the local
+/// data dir, dropping tokens that expired or whose game exited. Does nothing
+/// else until the listener is bound.
+fn publish_discovery() {
+    session_tokens().retain(|_, session| session.is_live());
+    let Some(port) = port() else {
+        return;
+    };
+    let dir = match crate::helpers::local_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::error!("Failed to write IPC discovery file: {}", e);
+            return;
+        }
+    };
+
+    let discovery = Discovery {
+        protocol_version: IPC_PROTOCOL_VERSION,
+        port,
+        launcher_pid: std::process::id(),
+        sessions: session_tokens()
+            .iter()
+            .map(|(token, session)| DiscoverySession {
+                pid: session.pid,
+                token: token.clone(),
+            })
+            .collect(),
+    };
+
+    if let Err(e) = write_discovery(&dir, &discovery) {
+        log::error!("Failed to write IPC discovery file: {}", e);
+    }
+}
+
+fn write_discovery(dir: &std::path::Path, discovery: &Discovery) -> crate::errors::Result<()> {
+    fs::create_dir_all(dir)?;
+    // written next to it and renamed, a client never reads half a file
+    let path = dir.join(IPC_DISCOVERY_FILE);
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, serde_json::to_vec(discovery)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temp, fs::Permissions::from_mode(0o600))?;
+    }
+    fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+/// Compares in time independent of where the tokens differ.
+fn tokens_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn authenticate(token: &str, pid: i32) -> std::result::Result<(), ProtocolError> {
+    if token.is_empty() {
+        return Err(ProtocolError::Unauthorized("no session token".to_string()));
+    }
+
+    let tokens = session_tokens();
+    let session = tokens
+        .iter()
+        .fold(None, |found, (issued, session)| {
+            if tokens_equal(issued, token) {
+                Some(session)
+            } else {
+                found
+            }
+        })
+        .ok_or_else(|| ProtocolError::Unauthorized("unknown session token".to_string()))?;
+
+    match session.pid {
+        Some(owner) if owner != pid => Err(ProtocolError::Unauthorized(format!(
+            "the session token was not issued to process {}",
+            pid
+        ))),
+        None if !session.is_live() => Err(ProtocolError::Unauthorized(
+            "the session token expired".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
 fn create_overlay_window(
     app: &tauri::AppHandle,
     label: &str,
@@ -62,117 +297,330 @@ fn create_overlay_window(
     Ok(())
 }
 
+/// Binds `IPC_PORT`, or a port the system picks if it is taken, so the
+/// in-game features keep working next to another program or launcher.
+fn bind_listener() -> std::io::Result<StdTcpListener> {
+    match StdTcpListener::bind(("127.0.0.1", IPC_PORT)) {
+        Ok(listener) => Ok(listener),
+        Err(e) => {
+            log::warn!(
+                "IPC port {} is unavailable ({}), using one assigned by the system",
+                IPC_PORT,
+                e
+            );
+            StdTcpListener::bind(("127.0.0.1", 0))
+        }
+    }
+}
+
 pub fn listen_for_ipc(app_handle: AppHandle) {
-    thread::spawn(move || {
-        let listener = match TcpListener::bind(format!("127.0.0.1:{}", IPC_PORT)) {
+    // bound before returning, a game launched right after gets the port
+    let listener = match bind_listener().and_then(|l| l.set_nonblocking(true).map(|_| l)) {
+        Ok(l) => l,
+        Err(e) => {
+            log::error!("Failed to bind to IPC port: {}", e);
+            return;
+        }
+    };
+    match listener.local_addr() {
+        Ok(addr) => {
+            log::info!("Listening for IPC on port {}", addr.port());
+            let _ = IPC_LISTEN_PORT.set(addr.port());
+            publish_discovery();
+        }
+        Err(e) => log::error!("Failed to get IPC port: {}", e),
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::from_std(listener) {
             Ok(l) => l,
             Err(e) => {
-                log::error!("Failed to bind to IPC port: {}", e);
+                log::error!("Failed to listen for IPC: {}", e);
                 return;
             }
         };
 
-        for stream in listener.incoming() {
-            if let Ok(stream) = stream {
-                let handle = app_handle.clone();
-
-                thread::spawn(move || {
-                    let Ok(stream_clone) = stream.try_clone() else {
-                        log::error!("Failed to clone IPC stream");
-                        return;
-                    };
-                    let mut reader = std::io::BufReader::new(stream_clone);
-                    let mut line = String::new();
-                    while let Ok(bytes) = reader.read_line(&mut line) {
-                        if bytes == 0 {
-                            break;
-                        }
-
-                        if line.starts_with("init:") {
-                            if let Some(pid_str) = line.strip_prefix("init:") {
-                                if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                                    if let Ok(stream_clone) = stream.try_clone() {
-                                        if let Ok(mut streams) = GAME_STREAMS.lock() {
-                                            streams.insert(pid, stream_clone);
-                                        }
-                                    }
-                                }
-                            }
-                        } else if line.starts_with("pos:") {
-                            if let Some(coords) = line.strip_prefix("pos:") {
-                                let parts: Vec<_> = coords.trim().split(',').collect();
-                                if parts.len() == 5 {
-                                    if let (Ok(_x), Ok(_y), Ok(w), Ok(h), Ok(pid)) = (
-                                        parts[0].parse::<i32>(),
-                                        parts[1].parse::<i32>(),
-                                        parts[2].parse::<i32>(),
-                                        parts[3].parse::<i32>(),
-                                        parts[4].parse::<i32>(),
-                                    ) {
-                                        let window_label = format!("omp_overlay_window:{}", pid);
-                                        if let Some(win) = handle.get_window(&window_label) {
-                                            // let _ = win.set_position(tauri::PhysicalPosition {
-                                            //     x: -1 * w - 1000,
-                                            //     y: -1 * h - 1000,
-                                            // });
-
-                                            let _ = win.set_size(tauri::PhysicalSize {
-                                                width: w as u32,
-                                                height: h as u32,
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                        } else if line.starts_with("show_overlay:") {
-                            let parts: Vec<_> = line.trim().split(':').collect();
-                            if parts.len() >= 2 {
-                                if let Ok(pid) = parts[1].parse::<i32>() {
-                                    let window_label = format!("omp_overlay_window:{}", pid);
-                                    let _ = create_overlay_window(&handle, &window_label, pid);
-                                }
-                            }
-                        } else if line.starts_with("hide_overlay:") {
-                            let parts: Vec<_> = line.trim().split(':').collect();
-                            if parts.len() >= 2 {
-                                let window_label = format!("omp_overlay_window:{}", parts[1]);
-                                if let Some(window) = handle.get_window(&window_label) {
-                                    let _ = window.close();
-                                } else {
-                                    log::warn!("IPC overlay window not found: {}", window_label);
-                                }
-                            }
-                        } else {
-                            log::warn!("Unknown IPC command received: {}", line.trim());
-                        }
-                        line.clear();
-                    }
-                });
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(app_handle.clone(), stream));
+                }
+                Err(e) => log::warn!("Failed to accept IPC connection: {}", e),
             }
         }
     });
 }
 
-#[tauri::command]
-pub fn send_message_to_game(id: i32, message: &str) -> std::result::Result<(), String> {
-    use std::io::Write;
+/// Queues `message` for the connection. Never waits, a reply that does not
+/// fit the queue is dropped.
+fn reply(outbound: &mpsc::Sender<String>, message: LauncherMessage, encoding: Encoding) {
+    if let Some(line) = message.encode(encoding) {
+        if let Err(e) = outbound.try_send(line) {
+            log::warn!("Failed to reply to IPC message: {}", e);
+        }
+    }
+}
 
-    let mut streams = GAME_STREAMS.lock().map_err(|_| {
-        crate::errors::LauncherError::InternalError("Failed to acquire stream lock".to_string())
-            .to_string()
-    })?;
+async fn handle_connection(handle: AppHandle, stream: TcpStream) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    let (read_half, write_half) = stream.into_split();
+    let (outbound, queued) = mpsc::channel(IPC_OUTBOUND_QUEUE_SIZE);
+    let mut connection = Connection {
+        id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        pid: None,
+        encoding: Encoding::Json,
+        outbound,
+    };
 
-    if let Some(stream) = streams.get_mut(&id) {
-        let full_message = format!("{}\n", message);
-        stream.write_all(full_message.as_bytes()).map_err(|e| {
-            crate::errors::LauncherError::Network(format!("Failed to write to stream: {}", e))
-                .to_string()
-        })?;
+    let writer = write_queued(write_half, queued);
+    tokio::pin!(writer);
+    let (reason, writer_done) = tokio::select! {
+        reason = read_messages(&handle, read_half, &mut connection, &peer) => (reason, false),
+        _ = &mut writer => (DisconnectReason::Error, true),
+    };
+
+    if let Some(pid) = connection.pid {
+        disconnect(&handle, pid, connection.id, reason);
+    }
+    // with every sender gone the writer ends once it has sent what is
+    // queued, like the reason for rejecting the connection
+    drop(connection);
+    if !writer_done {
+        let _ = timeout(Duration::from_millis(IPC_FLUSH_TIMEOUT_MS), writer).await;
+    }
+}
+
+async fn write_queued(mut writer: OwnedWriteHalf, mut queued: mpsc::Receiver<String>) {
+    while let Some(line) = queued.recv().await {
+        if let Err(e) = writer.write_all(line.as_bytes()).await {
+            log::warn!("Failed to write to IPC stream: {}", e);
+            return;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+async fn read_messages(
+    handle: &AppHandle,
+    reader: OwnedReadHalf,
+    connection: &mut Connection,
+    peer: &str,
+) -> DisconnectReason {
+    let mut reader = BufReader::new(reader);
+    // bytes, not a String, read_until keeps what it read when timed out
+    let mut line = Vec::new();
+    let heartbeat = Duration::from_millis(IPC_HEARTBEAT_TIMEOUT_MS);
+
+    loop {
+        // one byte over the limit tells a line that is too long
+        let limit = (IPC_MAX_LINE_LENGTH + 1 - line.len()) as u64;
+        let mut limited = (&mut reader).take(limit);
+        match timeout(heartbeat, limited.read_until(b'\n', &mut line)).await {
+            Ok(Ok(0)) => return DisconnectReason::Closed,
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                log::warn!("IPC connection from {} failed: {}", peer, e);
+                return DisconnectReason::Error;
+            }
+            Err(_) => {
+                // legacy clients send no heartbeat, they stay while the game runs
+                let alive = connection.encoding == Encoding::Legacy
+                    && connection
+                        .pid
+                        .is_some_and(|pid| crate::commands::is_process_alive(pid as u32));
+                if alive {
+                    continue;
+                }
+                log::warn!("IPC connection from {} timed out", peer);
+                return DisconnectReason::TimedOut;
+            }
+        }
+
+        if line.len() > IPC_MAX_LINE_LENGTH {
+            let e =
+                ProtocolError::Malformed(format!("line longer than {} bytes", IPC_MAX_LINE_LENGTH));
+            log::warn!("Closing IPC connection from {}: {}", peer, e);
+            reply(&connection.outbound, e.into(), connection.encoding);
+            return DisconnectReason::Error;
+        }
+
+        let text = String::from_utf8_lossy(&line).into_owned();
+        line.clear();
+        let encoding = ipc_protocol::encoding_of(&text);
+        match handle_line(handle, &text, connection) {
+            Ok(()) => {}
+            // the line is left out of the log, a hello holds a session token
+            Err(e @ ProtocolError::Unauthorized(_)) => {
+                log::warn!("Rejected IPC connection from {}: {}", peer, e);
+                reply(&connection.outbound, e.into(), encoding);
+                return DisconnectReason::Closed;
+            }
+            Err(e) => {
+                log::warn!("Rejected IPC message from {}: {}", peer, e);
+                reply(&connection.outbound, e.into(), encoding);
+            }
+        }
+    }
+}
+
+/// Forgets the game's connection and session tokens unless it has reconnected
+/// since, and closes its overlay.
+fn disconnect(handle: &AppHandle, pid: i32, connection_id: u64, reason: DisconnectReason) {
+    let removed = match GAME_STREAMS.lock() {
+        Ok(mut streams) => {
+            let current = streams
+                .get(&pid)
+                .is_some_and(|game| game.connection_id == connection_id);
+            current && streams.remove(&pid).is_some()
+        }
+        Err(_) => false,
+    };
+    if !removed {
+        return;
+    }
+
+    log::info!("Game {} disconnected from IPC", pid);
+    revoke_session_tokens_of(pid);
+    if let Some(window) = handle.get_window(&format!("omp_overlay_window:{}", pid)) {
+        let _ = window.close();
+    }
+    let _ = handle.emit_all("game-disconnected", GameDisconnected { pid, reason });
+}
+
+fn handle_line(
+    handle: &AppHandle,
+    line: &str,
+    connection: &mut Connection,
+) -> std::result::Result<(), ProtocolError> {
+    let incoming = ipc_protocol::decode(line)?;
+
+    if let GameMessage::Hello {
+        version,
+        pid,
+        ref token,
+    } = incoming.message
+    {
+        if connection.pid.is_some() {
+            return Err(ProtocolError::AlreadyInitialized);
+        }
+        let version = match incoming.encoding {
+            Encoding::Json => ipc_protocol::negotiate(version)?,
+            Encoding::Legacy => 0,
+        };
+        authenticate(token, pid)?;
+        if incoming.encoding == Encoding::Json {
+            let welcome = LauncherMessage::Welcome { version };
+            reply(&connection.outbound, welcome, Encoding::Json);
+        }
+        if let Ok(mut streams) = GAME_STREAMS.lock() {
+            streams.insert(
+                pid,
+                GameStream {
+                    outbound: connection.outbound.clone(),
+                    encoding: incoming.encoding,
+                    connection_id: connection.id,
+                },
+            );
+        }
+        connection.pid = Some(pid);
+        connection.encoding = incoming.encoding;
+        let _ = handle.emit_all("game-connected", GameConnected { pid, version });
         return Ok(());
-    } else {
-        log::warn!("No IPC stream found for process ID: {}", id);
-        return Err(
-            crate::errors::LauncherError::NotFound("no_stream_found".to_string()).to_string(),
-        );
     }
+
+    let pid = connection.pid.ok_or(ProtocolError::NotInitialized)?;
+    // legacy commands name their process, it has to be the connection's
+    if incoming.pid.is_some_and(|named| named != pid) {
+        return Err(ProtocolError::Unauthorized(format!(
+            "the connection belongs to process {}",
+            pid
+        )));
+    }
+    let window_label = format!("omp_overlay_window:{}", pid);
+
+    match incoming.message {
+        GameMessage::Hello { .. } => {}
+        GameMessage::Position { width, height, .. } => {
+            if let Some(win) = handle.get_window(&window_label) {
+                let _ = win.set_size(tauri::PhysicalSize { width, height });
+            }
+        }
+        GameMessage::ShowOverlay => {
+            let _ = create_overlay_window(handle, &window_label, pid);
+        }
+        GameMessage::HideOverlay => {
+            if let Some(window) = handle.get_window(&window_label) {
+                let _ = window.close();
+            } else {
+                log::warn!("IPC overlay window not found: {}", window_label);
+            }
+        }
+        GameMessage::Ping => reply(
+            &connection.outbound,
+            LauncherMessage::Pong,
+            connection.encoding,
+        ),
+    }
+    Ok(())
+}
+
+/// What the frontend sends to a game: a typed message, or a text command as
+/// it used to send them.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum OutgoingMessage {
+    Typed(LauncherMessage),
+    Text(String),
+}
+
+/// Queues `message` for the game and returns right away, the game's
+/// connection task writes it.
+#[tauri::command]
+pub fn send_message_to_game(id: i32, message: OutgoingMessage) -> std::result::Result<(), String> {
+    let (outbound, encoding) = {
+        let streams = GAME_STREAMS.lock().map_err(|_| {
+            crate::errors::LauncherError::InternalError("Failed to acquire stream lock".to_string())
+                .to_string()
+        })?;
+
+        let Some(game) = streams.get(&id) else {
+            log::warn!("No IPC stream found for process ID: {}", id);
+            return Err(
+                crate::errors::LauncherError::NotFound("no_stream_found".to_string()).to_string(),
+            );
+        };
+        (game.outbound.clone(), game.encoding)
+    };
+
+    let line = match (&message, encoding) {
+        // legacy clients get text commands exactly as before
+        (OutgoingMessage::Text(text), Encoding::Legacy) => Some(format!("{}\n", text)),
+        (OutgoingMessage::Text(text), encoding) => {
+            LauncherMessage::from_legacy(text).and_then(|message| message.encode(encoding))
+        }
+        (OutgoingMessage::Typed(message), encoding) => message.encode(encoding),
+    };
+    let Some(line) = line else {
+        return Err(crate::errors::LauncherError::InvalidInput(format!(
+            "{:?} can't be sent to this game client",
+            message
+        ))
+        .to_string());
+    };
+
+    outbound.try_send(line).map_err(|e| match e {
+        TrySendError::Full(_) => {
+            log::warn!("IPC queue of process ID {} is full", id);
+            crate::errors::LauncherError::Network(
+                "The game is not reading its messages".to_string(),
+            )
+            .to_string()
+        }
+        TrySendError::Closed(_) => {
+            crate::errors::LauncherError::NotFound("no_stream_found".to_string()).to_string()
+        }
+    })
 }
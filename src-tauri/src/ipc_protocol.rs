@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::constants::*;
+
+/// How a connection frames its messages, decided by its first message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    /// `command:arg,arg` text lines, spoken by clients from before the JSON
+    /// protocol and answered in kind.
+    Legacy,
+    /// One JSON object per line.
+    Json,
+}
+
+/// A message from the game client, one JSON object per line tagged with
+/// `type`. A client starts with `hello` and gets a `welcome` with the version
+/// both sides speak, or an `error`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameMessage {
+    /// Starts the connection. `version` is the newest protocol version the
+    /// client speaks, 0 for the legacy `init:` command. `token` is the one
+    /// the launcher passed to the game in `IPC_TOKEN_ENV`.
+    Hello {
+        version: u32,
+        pid: i32,
+        #[serde(default)]
+        token: String,
+    },
+    /// The game window was moved or resized.
+    Position {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    ShowOverlay,
+    HideOverlay,
+    /// Keeps the connection alive, answered with `pong`. A client that sends
+    /// nothing for `IPC_HEARTBEAT_TIMEOUT_MS` is disconnected.
+    Ping,
+}
+
+impl GameMessage {
+    const TYPES: [&'static str; 5] = ["hello", "position", "show_overlay", "hide_overlay", "ping"];
+}
+
+/// A message to the game client.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LauncherMessage {
+    /// Answers `hello` with the version used from now on.
+    Welcome {
+        version: u32,
+    },
+    Connect {
+        host: String,
+        port: u16,
+        nickname: String,
+        password: Option<String>,
+    },
+    Reconnect,
+    CloseOverlay,
+    Pong,
+    Error {
+        code: String,
+        message: String,
+    },
+}
+
+impl LauncherMessage {
+    /// Parses a text command as the frontend used to send them, e.g.
+    /// `connect:127.0.0.1:7777:nick:password`. The password is everything
+    /// after the nickname, a host can't contain ':'.
+    pub fn from_legacy(text: &str) -> Option<Self> {
+        let text = text.trim();
+        match text {
+            "reconnect" => return Some(LauncherMessage::Reconnect),
+            "close_overlay" => return Some(LauncherMessage::CloseOverlay),
+            _ => {}
+        }
+
+        let mut parts = text.strip_prefix("connect:")?.splitn(4, ':');
+        let host = parts.next()?.to_string();
+        let port = parts.next()?.parse().ok()?;
+        let nickname = parts.next()?.to_string();
+        let password = parts.next().map(str::to_string);
+        Some(LauncherMessage::Connect {
+            host,
+            port,
+            nickname,
+            password,
+        })
+    }
+
+    /// The line to send, `None` if a legacy client has no equivalent. That
+    /// includes a `connect` whose host or nickname contains ':', which a
+    /// legacy client would split in the wrong place, or any field with a
+    /// line break.
+    pub fn encode(&self, encoding: Encoding) -> Option<String> {
+        let line = match encoding {
+            Encoding::Json => serde_json::to_string(self).ok()?,
+            Encoding::Legacy => match self {
+                LauncherMessage::Welcome { .. } | LauncherMessage::Pong => return None,
+                LauncherMessage::Connect {
+                    host,
+                    port,
+                    nickname,
+                    password,
+                } => {
+                    let breaks = |field: &str| field.contains(['\r', '\n']);
+                    let splits = |field: &str| breaks(field) || field.contains(':');
+                    if splits(host) || splits(nickname) || password.as_deref().is_some_and(breaks) {
+                        return None;
+                    }
+                    match password {
+                        Some(password) => {
+                            format!("connect:{}:{}:{}:{}", host, port, nickname, password)
+                        }
+                        None => format!("connect:{}:{}:{}", host, port, nickname),
+                    }
+                }
+                LauncherMessage::Reconnect => "reconnect".to_string(),
+                LauncherMessage::CloseOverlay => "close_overlay".to_string(),
+                LauncherMessage::Error { code, .. } => format!("error:{}", code),
+            },
+        };
+        Some(line + "\n")
+    }
+}
+
+impl From<ProtocolError> for LauncherMessage {
+    fn from(err: ProtocolError) -> Self {
+        LauncherMessage::Error {
+            code: err.code().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    Malformed(String),
+    UnknownType(String),
+    UnsupportedVersion(u32),
+    /// A message that needs the connection's process came before `hello`.
+    NotInitialized,
+    /// A second `hello` on a connection.
+    AlreadyInitialized,
+    /// `hello` without the session token of its process. The connection is
+    /// closed after the reply.
+    Unauthorized(String),
+}
+
+impl ProtocolError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProtocolError::Malformed(_) => "malformed",
+            ProtocolError::UnknownType(_) => "unknown_type",
+            ProtocolError::UnsupportedVersion(_) => "unsupported_version",
+            ProtocolError::NotInitialized => "not_initialized",
+            ProtocolError::AlreadyInitialized => "already_initialized",
+            ProtocolError::Unauthorized(_) => "unauthorized",
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Malformed(msg) => write!(f, "Malformed message: {}", msg),
+            ProtocolError::UnknownType(name) => write!(f, "Unknown message type '{}'", name),
+            ProtocolError::UnsupportedVersion(version) => write!(
+                f,
+                "Protocol version {} is not supported, expected 1 to {}",
+                version, IPC_PROTOCOL_VERSION
+            ),
+            ProtocolError::NotInitialized => write!(f, "Expected hello first"),
+            ProtocolError::AlreadyInitialized => write!(f, "Already said hello"),
+            ProtocolError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// A decoded line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incoming {
+    pub message: GameMessage,
+    pub encoding: Encoding,
+    /// The process a legacy command names. JSON messages are about the
+    /// process of their connection.
+    pub pid: Option<i32>,
+}
+
+/// The encoding of `line`, to answer it in kind even if it can't be decoded.
+pub fn encoding_of(line: &str) -> Encoding {
+    if line.trim_start().starts_with('{') {
+        Encoding::Json
+    } else {
+        Encoding::Legacy
+    }
+}
+
+pub fn decode(line: &str) -> Result<Incoming, ProtocolError> {
+    let line = line.trim();
+    match encoding_of(line) {
+        Encoding::Json => decode_json(line),
+        Encoding::Legacy => decode_legacy(line),
+    }
+}
+
+fn decode_json(line: &str) -> Result<Incoming, ProtocolError> {
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    let Some(kind) = value.get("type").and_then(|kind| kind.as_str()) else {
+        return Err(ProtocolError::Malformed("missing type".to_string()));
+    };
+    if !GameMessage::TYPES.contains(&kind) {
+        return Err(ProtocolError::UnknownType(kind.to_string()));
+    }
+
+    let message =
+        serde_json::from_value(value).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    Ok(Incoming {
+        message,
+        encoding: Encoding::Json,
+        pid: None,
+    })
+}
+
+fn decode_legacy(line: &str) -> Result<Incoming, ProtocolError> {
+    let (command, args) = line.split_once(':').unwrap_or((line, ""));
+    let parse_pid = |pid: &str| {
+        pid.trim()
+            .parse::<i32>()
+            .map_err(|_| ProtocolError::Malformed(format!("invalid process id '{}'", pid)))
+    };
+
+    let (message, pid) = match command {
+        // init:<pid>:<token>
+        "init" => {
+            let (pid, token) = args.split_once(':').unwrap_or((args, ""));
+            let pid = parse_pid(pid)?;
+            let token = token.trim().to_string();
+            (
+                GameMessage::Hello {
+                    version: 0,
+                    pid,
+                    token,
+                },
+                pid,
+            )
+        }
+        "pos" => {
+            let parts: Vec<_> = args.split(',').map(str::trim).collect();
+            let malformed = || ProtocolError::Malformed(format!("invalid position '{}'", args));
+            let [x, y, width, height, pid] = parts[..] else {
+                return Err(malformed());
+            };
+            (
+                GameMessage::Position {
+                    x: x.parse().map_err(|_| malformed())?,
+                    y: y.parse().map_err(|_| malformed())?,
+                    width: width.parse().map_err(|_| malformed())?,
+                    height: height.parse().map_err(|_| malformed())?,
+                },
+                parse_pid(pid)?,
+            )
+        }
+        "show_overlay" => (GameMessage::ShowOverlay, parse_pid(args)?),
+        "hide_overlay" => (GameMessage::HideOverlay, parse_pid(args)?),
+        other => return Err(ProtocolError::UnknownType(other.to_string())),
+    };
+
+    Ok(Incoming {
+        message,
+        encoding: Encoding::Legacy,
+        pid: Some(pid),
+    })
+}
+
+/// The version to speak with a client that speaks up to `version`.
+pub fn negotiate(version: u32) -> Result<u32, ProtocolError> {
+    match version.min(IPC_PROTOCOL_VERSION) {
+        0 => Err(ProtocolError::UnsupportedVersion(version)),
+        negotiated => Ok(negotiated),
+    }
+}
@@ -0,0 +1,230 @@
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Manager;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant, MissedTickBehavior};
+
+use crate::query::{InfoPacket, OutstandingRequest, Query, QueryPacket};
+use crate::{constants::*, errors::*};
+
+static SCAN_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Clone)]
+pub struct LanServer {
+    pub scan_id: u32,
+    pub ip: String,
+    pub port: i32,
+    pub info: InfoPacket,
+}
+
+/// Clears `SCAN_RUNNING` when the scan ends, however it ends.
+struct ScanGuard;
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        SCAN_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Every host address of the private IPv4 subnets this machine is on.
+/// Subnets larger than `/LAN_SCAN_MIN_PREFIX` are narrowed down to the part
+/// around our own address, nobody wants to probe 65k hosts. This machine is
+/// probed once, as 127.0.0.1, so servers running next to the launcher are
+/// found too.
+fn local_hosts() -> Result<Vec<Ipv4Addr>> {
+    let interfaces = NetworkInterface::show()
+        .map_err(|e| LauncherError::Network(format!("Failed to list network interfaces: {}", e)))?;
+
+    let mut hosts = vec![Ipv4Addr::LOCALHOST];
+    let mut seen = HashSet::new();
+    for interface in interfaces {
+        for addr in interface.addr {
+            let Addr::V4(v4) = addr else {
+                continue;
+            };
+
+            let ip = v4.ip;
+            if !ip.is_private() || ip.is_loopback() {
+                continue;
+            }
+
+            let prefix = v4
+                .netmask
+                .map(|mask| u32::from(mask).leading_ones())
+                .unwrap_or(24)
+                .max(LAN_SCAN_MIN_PREFIX);
+            if prefix >= 31 {
+                continue;
+            }
+
+            let mask = u32::MAX << (32 - prefix);
+            let network = u32::from(ip) & mask;
+            let broadcast = network | !mask;
+            for host in (network + 1)..broadcast {
+                let host = Ipv4Addr::from(host);
+                if host != ip && seen.insert(host) {
+                    hosts.push(host);
+                }
+            }
+        }
+    }
+    Ok(hosts)
+}
+
+/// Probes `ports` on every host of the local subnets with an info ('i')
+/// query, at most `LAN_SCAN_PROBES_PER_SEC` probes per second. `on_found` is
+/// called for each server as soon as it answers. Returns every server that
+/// answered before `deadline`.
+pub async fn scan<F>(
+    hosts: &[Ipv4Addr],
+    ports: std::ops::RangeInclusive<u16>,
+    deadline: Instant,
+    mut on_found: F,
+) -> Result<HashMap<SocketAddr, InfoPacket>>
+where
+    F: FnMut(SocketAddr, &InfoPacket),
+{
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| LauncherError::Network(format!("Failed to bind socket: {}", e)))?;
+
+    let mut targets = hosts.iter().flat_map(|&host| {
+        ports
+            .clone()
+            .map(move |port| SocketAddr::from((host, port)))
+    });
+
+    let mut found = HashMap::new();
+    let mut ticker = tokio::time::interval(Duration::from_secs(1) / LAN_SCAN_PROBES_PER_SEC);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut sending = true;
+    let mut quiet_until = deadline;
+    let mut buf = [0; UDP_BUFFER_SIZE];
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick(), if sending => {
+                match targets.next() {
+                    Some(target) => {
+                        let request = OutstandingRequest::new(target, QUERY_TYPE_INFO);
+                        // unreachable hosts and closed ports are expected here
+                        let _ = socket.send_to(&request.packet(), target).await;
+                    }
+                    None => {
+                        sending = false;
+                        quiet_until = deadline
+                            .min(Instant::now() + Duration::from_millis(LAN_SCAN_REPLY_WAIT_MS));
+                    }
+                }
+            }
+            received = timeout_at(quiet_until, socket.recv_from(&mut buf)) => {
+                let (amt, src) = match received {
+                    Ok(Ok(r)) => r,
+                    // ICMP port unreachable from a probed host, keep going
+                    Ok(Err(_)) => continue,
+                    Err(_) => break,
+                };
+
+                if found.contains_key(&src)
+                    || !OutstandingRequest::new(src, QUERY_TYPE_INFO).matches(&buf[..amt])
+                {
+                    continue;
+                }
+
+                let body = Cursor::new(buf[QUERY_HEADER_LENGTH..amt].to_vec());
+                if let Ok(QueryPacket::Info(info)) = Query::parse_packet(QUERY_TYPE_INFO, body) {
+                    on_found(src, &info);
+                    found.insert(src, info);
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// How long probing `probes` targets at `LAN_SCAN_PROBES_PER_SEC` and waiting
+/// for the last replies takes.
+fn scan_duration(probes: usize) -> Duration {
+    Duration::from_secs(1) * probes as u32 / LAN_SCAN_PROBES_PER_SEC
+        + Duration::from_millis(LAN_SCAN_REPLY_WAIT_MS)
+}
+
+/// Looks for servers on the local network. Every server found is emitted as
+/// a `lan-server-found` event tagged with `scan_id` right away, and all of
+/// them are returned once the scan is done. Unless `timeout_ms` is given, the
+/// scan gets as long as probing every host takes. Only one scan runs at a
+/// time.
+#[tauri::command]
+pub async fn scan_lan(
+    app_handle: tauri::AppHandle,
+    scan_id: u32,
+    port_from: Option<u16>,
+    port_to: Option<u16>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<LanServer>> {
+    let port_from = port_from.unwrap_or(LAN_SCAN_DEFAULT_PORT_FROM);
+    let port_to = port_to.unwrap_or(LAN_SCAN_DEFAULT_PORT_TO);
+    if port_from == 0 || port_from > port_to || port_to - port_from >= LAN_SCAN_MAX_PORTS {
+        return Err(LauncherError::InvalidInput(format!(
+            "Invalid port range {}-{}, at most {} ports can be scanned",
+            port_from, port_to, LAN_SCAN_MAX_PORTS
+        )));
+    }
+
+    if SCAN_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(LauncherError::InvalidInput(
+            "A LAN scan is already running".to_string(),
+        ));
+    }
+    let _guard = ScanGuard;
+
+    let hosts = local_hosts()?;
+    let needed = scan_duration(hosts.len() * (port_to - port_from + 1) as usize);
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(needed)
+        .min(Duration::from_millis(LAN_SCAN_MAX_TIMEOUT_MS));
+    let deadline = Instant::now() + timeout;
+    log::info!(
+        "Scanning {} LAN hosts on ports {}-{} for up to {:?}",
+        hosts.len(),
+        port_from,
+        port_to,
+        timeout
+    );
+    if timeout < needed {
+        log::warn!(
+            "The LAN scan needs {:?} to probe every host and will stop early",
+            needed
+        );
+    }
+
+    let found = scan(&hosts, port_from..=port_to, deadline, |addr, info| {
+        let _ = app_handle.emit_all(
+            "lan-server-found",
+            LanServer {
+                scan_id,
+                ip: addr.ip().to_string(),
+                port: addr.port() as i32,
+                info: info.clone(),
+            },
+        );
+    })
+    .await?;
+
+    Ok(found
+        .into_iter()
+        .map(|(addr, info)| LanServer {
+            scan_id,
+            ip: addr.ip().to_string(),
+            port: addr.port() as i32,
+            info,
+        })
+        .collect())
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    constants::*,
+    errors::*,
+    settings,
+    uri::{self, ClientVersion, ServerUri},
+};
+
+/// Held while checking and storing the time of the last link, see [`admit()`].
+static ADMIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HostListMode {
+    /// Links to any host not in `blocked_hosts` open without asking about
+    /// the host.
+    #[default]
+    Blocklist,
+    /// Only links to hosts in `allowed_hosts` open without asking about the
+    /// host.
+    Allowlist,
+}
+
+/// How links opened from outside the launcher are treated. Host entries are
+/// either `host` or `host:port` and compared ignoring case.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LinkPolicy {
+    pub mode: HostListMode,
+    pub allowed_hosts: Vec<String>,
+    /// Links to these hosts are dropped in either mode.
+    pub blocked_hosts: Vec<String>,
+}
+
+impl LinkPolicy {
+    fn lists(list: &[String], uri: &ServerUri) -> bool {
+        let address = format!("{}:{}", uri.host, uri.port);
+        list.iter().map(|entry| entry.trim()).any(|entry| {
+            entry.eq_ignore_ascii_case(&uri.host) || entry.eq_ignore_ascii_case(&address)
+        })
+    }
+
+    fn normalize(list: Vec<String>) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for entry in list {
+            let entry = entry.trim().to_lowercase();
+            if !entry.is_empty() && !normalized.contains(&entry) {
+                normalized.push(entry);
+            }
+        }
+        normalized
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkVerdict {
+    /// Nothing to ask about, the link may launch the game right away.
+    Allow,
+    /// The user has to confirm the link first, see `flags`.
+    Confirm,
+    /// The link must not be opened, see `reason`.
+    Block,
+}
+
+/// What the user should know about a link before opening it.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LinkFlag {
+    /// The host is not in the allowlist.
+    UnlistedHost,
+    /// The link fills in the server password.
+    Password,
+    /// The link replaces the user's nickname.
+    Nickname { nickname: String },
+    /// The link picks the client version.
+    Version { version: ClientVersion },
+}
+
+/// The outcome of checking a link against the [`LinkPolicy`], sent to the
+/// frontend with `scheme-request-received`.
+#[derive(Serialize, Clone, Debug)]
+pub struct LinkCheck {
+    pub link: String,
+    /// `None` if the link could not be parsed.
+    pub uri: Option<ServerUri>,
+    pub verdict: LinkVerdict,
+    pub flags: Vec<LinkFlag>,
+    pub reason: Option<String>,
+}
+
+impl LinkCheck {
+    fn blocked(link: &str, uri: Option<ServerUri>, reason: String) -> Self {
+        Self {
+            link: link.to_string(),
+            uri,
+            verdict: LinkVerdict::Block,
+            flags: Vec::new(),
+            reason: Some(reason),
+        }
+    }
+}
+
+/// Checks `link` against the stored policy.
+pub fn evaluate(link: &str) -> LinkCheck {
+    let uri = match uri::parse(link) {
+        Ok(uri) => uri,
+        Err(e) => return LinkCheck::blocked(link, None, e.to_string()),
+    };
+
+    let policy = settings::load().link_policy;
+    if LinkPolicy::lists(&policy.blocked_hosts, &uri) {
+        let reason = format!("{}:{} is blocked", uri.host, uri.port);
+        return LinkCheck::blocked(link, Some(uri), reason);
+    }
+
+    let mut flags = Vec::new();
+    if policy.mode == HostListMode::Allowlist && !LinkPolicy::lists(&policy.allowed_hosts, &uri) {
+        flags.push(LinkFlag::UnlistedHost);
+    }
+    if uri.password.is_some() {
+        flags.push(LinkFlag::Password);
+    }
+    if let Some(nickname) = &uri.nickname {
+        flags.push(LinkFlag::Nickname {
+            nickname: nickname.clone(),
+        });
+    }
+    if let Some(version) = uri.version {
+        flags.push(LinkFlag::Version { version });
+    }
+
+    LinkCheck {
+        link: link.to_string(),
+        uri: Some(uri),
+        verdict: if flags.is_empty() {
+            LinkVerdict::Allow
+        } else {
+            LinkVerdict::Confirm
+        },
+        flags,
+        reason: None,
+    }
+}
+
+/// Checks a link that was just opened from outside the launcher. Unlike
+/// [`evaluate()`] it also blocks links arriving within `LINK_COOLDOWN_MS` of
+/// the last one let through, by this or any other launcher process.
+pub fn admit(link: &str) -> LinkCheck {
+    let check = evaluate(link);
+    if check.verdict == LinkVerdict::Block {
+        return check;
+    }
+
+    let _lock = match ADMIT_LOCK.lock() {
+        Ok(lock) => lock,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let mut settings = settings::load();
+    // a last link in the future means the clock was turned back
+    if settings
+        .last_link_at
+        .is_some_and(|at| at <= now && now - at < LINK_COOLDOWN_MS)
+    {
+        return LinkCheck::blocked(
+            link,
+            check.uri,
+            "Too many links in a short time, try again in a moment".to_string(),
+        );
+    }
+
+    settings.last_link_at = Some(now);
+    if let Err(e) = settings::save(&settings) {
+        log::warn!("Failed to store the time of the last link: {}", e);
+    }
+    check
+}
+
+#[tauri::command]
+pub fn check_server_link(uri: String) -> LinkCheck {
+    evaluate(&uri)
+}
+
+#[tauri::command]
+pub fn get_link_policy() -> LinkPolicy {
+    settings::load().link_policy
+}
+
+#[tauri::command]
+pub fn set_link_policy(policy: LinkPolicy) -> Result<LinkPolicy> {
+    let mut settings = settings::load();
+    settings.link_policy = LinkPolicy {
+        mode: policy.mode,
+        allowed_hosts: LinkPolicy::normalize(policy.allowed_hosts),
+        blocked_hosts: LinkPolicy::normalize(policy.blocked_hosts),
+    };
+    settings::save(&settings)?;
+    Ok(settings.link_policy)
+}
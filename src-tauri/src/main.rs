@@ -1,31 +1,46 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod batch_query;
 mod cli;
 mod commands;
 mod constants;
 mod errors;
+mod favorites;
 mod helpers;
 mod injector;
 mod ipc;
+mod ipc_protocol;
+mod lan;
+mod link_policy;
+mod master_list;
+mod ping;
 mod query;
+mod query_cache;
+mod rcon;
+mod retransmit;
 mod samp;
+mod schemes;
+mod server_history;
+mod server_watch;
+mod settings;
+mod uri;
 mod validation;
 
 #[path = "deeplink/lib.rs"]
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 mod deeplink;
 
 use std::env;
 use std::process::exit;
 use std::sync::Mutex;
 
-use cli::CliArgs;
+use cli::{CliArgs, Command};
 use constants::*;
 use errors::{LauncherError, Result};
 use gumdrop::Options;
-use injector::run_samp;
-use log::{error, info, LevelFilter};
+use link_policy::{LinkCheck, LinkVerdict};
+use log::{error, info, warn, LevelFilter};
 use std::fs;
 use tauri::api::path::app_data_dir;
 use tauri::Manager;
@@ -49,7 +64,7 @@ async fn main() {
     // let digest = md5::compute(contents.as_slice());
     // println!("{:x}", digest);
 
-    #[cfg(windows)]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     {
         deeplink::prepare(DEEPLINK_IDENTIFIER);
     }
@@ -68,6 +83,9 @@ async fn main() {
         }
     }
 
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    forward_to_primary();
+
     if let Err(e) = handle_cli_args().await {
         error!("CLI error: {}", e);
         exit(1);
@@ -102,66 +120,44 @@ async fn handle_cli_args() -> Result<()> {
                 }
             }
 
-            if args.help {
-                CliArgs::print_help_and_exit(&raw_args[0]);
+            if args.help_requested() {
+                cli::print_help(&args, &raw_args[0]);
+                exit(CLI_EXIT_SUCCESS);
             }
 
-            if args.has_game_launch_args() {
-                let gamepath = args.gamepath.as_ref().unwrap();
-                let password = args.get_password();
-
-                let omp_client_path = format!(
-                    "{}/{}/omp/{}",
-                    dirs_next::data_local_dir()
-                        .ok_or(LauncherError::InternalError(
-                            "Failed to get data directory".to_string()
-                        ))?
-                        .to_str()
-                        .ok_or(LauncherError::InternalError(
-                            "Invalid data directory path".to_string()
-                        ))?,
-                    DATA_DIR_NAME,
-                    OMP_CLIENT_DLL
-                );
+            match &args.command {
+                Some(Command::Uri(uri_args)) if !uri_args.direct => {
+                    let link = uri_args.uri.clone().unwrap_or_default();
+                    if let Err(e) = uri::parse(&link) {
+                        eprintln!("uri: {}", e);
+                        exit(CLI_EXIT_USAGE);
+                    }
+                    open_link(link).await;
+                }
+                Some(command) => exit(cli::run_command(command).await),
+                None => {}
+            }
 
-                let omp_path = if args.no_omp {
-                    ""
-                } else {
-                    &omp_client_path
-                };
-
-                // resolve hostname to ipv4 so the game does not truncate hyphenated hosts
-                let host = args.host.as_ref().unwrap();
-                let resolved_host =
-                    helpers::resolve_hostname_to_ipv4(host).unwrap_or_else(|e| {
-                        info!("Failed to resolve hostname '{}', using raw value: {}", host, e);
-                        host.clone()
-                    });
-
-                run_samp(
-                    args.name.as_ref().unwrap(),
-                    &resolved_host,
-                    args.port.unwrap(),
-                    gamepath,
-                    &format!("{}/{}", gamepath, SAMP_DLL),
-                    omp_path,
-                    &password,
-                    "",
-                )
-                .await
-                .map_err(|e| LauncherError::InternalError(e.to_string()))?;
-
-                info!("Successfully launched game from command line");
+            if args.has_game_launch_args() {
+                cli::launch(&args.launch_args()).await?;
                 exit(0);
             }
         }
         Err(e) => {
-            if raw_args.len() > 1
+            if raw_args.len() > 2 && raw_args[1] == "--direct" && uri::is_server_uri(&raw_args[2]) {
+                let direct = cli::Command::Uri(cli::UriArgs {
+                    help: false,
+                    direct: true,
+                    uri: Some(raw_args[2].clone()),
+                });
+                exit(cli::run_command(&direct).await);
+            } else if raw_args.len() > 1
                 && (raw_args[1].contains("omp://") || raw_args[1].contains("samp://"))
             {
-                if let Ok(mut uri_scheme_value) = URI_SCHEME_VALUE.lock() {
-                    *uri_scheme_value = raw_args[1].clone();
-                }
+                open_link(raw_args[1].clone()).await;
+            } else if raw_args.len() > 1 && cli::is_command(&raw_args[1]) {
+                eprintln!("{}: {}", raw_args[1], e);
+                exit(CLI_EXIT_USAGE);
             } else {
                 info!("Invalid CLI arguments: {}", e);
             }
@@ -171,6 +167,98 @@ async fn handle_cli_args() -> Result<()> {
     Ok(())
 }
 
+/// What the arguments ask a running launcher to do, `None` for commands that
+/// run in this process regardless.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn handoff_intent(raw_args: &[String]) -> Option<deeplink::Intent> {
+    let Some(first) = raw_args.get(1) else {
+        return Some(deeplink::Intent::Focus);
+    };
+    if uri::is_server_uri(first) {
+        return Some(deeplink::Intent::OpenUri { uri: first.clone() });
+    }
+
+    let args = CliArgs::parse_args_default::<String>(&raw_args[1..]).ok()?;
+    if args.help_requested() {
+        return None;
+    }
+    match &args.command {
+        Some(Command::Uri(uri_args)) if !uri_args.direct => uri_args
+            .uri
+            .clone()
+            .map(|uri| deeplink::Intent::OpenUri { uri }),
+        Some(_) => None,
+        None if args.has_game_launch_args() => Some(deeplink::Intent::Launch),
+        None => None,
+    }
+}
+
+/// Hands the arguments off to the primary instance and exits, if there is
+/// one and they are meant for it.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn forward_to_primary() {
+    let raw_args: Vec<String> = env::args().collect();
+    let Some(intent) = handoff_intent(&raw_args) else {
+        return;
+    };
+
+    match deeplink::forward(intent) {
+        None => {}
+        Some(Ok(ack)) if ack.ok => exit(CLI_EXIT_SUCCESS),
+        Some(Ok(ack)) => {
+            let reason = ack.error.unwrap_or_else(|| "no reason given".to_string());
+            eprintln!("The running launcher rejected the request: {}", reason);
+            exit(CLI_EXIT_HANDOFF_REJECTED);
+        }
+        Some(Err(e)) => {
+            error!("Failed to hand off to the running launcher: {}", e);
+            eprintln!("Failed to hand off to the running launcher: {}", e);
+            exit(CLI_EXIT_HANDOFF_FAILED);
+        }
+    }
+}
+
+/// Launches the game straight from a checked link if the `direct_links`
+/// setting is on and the policy has nothing to ask about. Returns whether
+/// the game was launched.
+async fn launch_link_directly(check: &LinkCheck) -> bool {
+    let (LinkVerdict::Allow, Some(uri)) = (check.verdict, &check.uri) else {
+        return false;
+    };
+    if !settings::load().direct_links {
+        return false;
+    }
+
+    let no_omp = NO_OMP_FLAG.lock().is_ok_and(|flag| *flag);
+    match cli::launch_uri(uri, no_omp).await {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Failed to launch {} directly: {}", check.link, e);
+            false
+        }
+    }
+}
+
+/// Launches the game from `link` and exits if possible, otherwise leaves the
+/// link to the launcher window. Blocked links are dropped.
+async fn open_link(link: String) {
+    let check = link_policy::admit(&link);
+    if check.verdict == LinkVerdict::Block {
+        let reason = check.reason.unwrap_or_default();
+        warn!("Ignoring link {}: {}", link, reason);
+        eprintln!("Ignoring link: {}", reason);
+        return;
+    }
+
+    if launch_link_directly(&check).await {
+        exit(CLI_EXIT_SUCCESS);
+    }
+
+    if let Ok(mut uri_scheme_value) = URI_SCHEME_VALUE.lock() {
+        *uri_scheme_value = link;
+    }
+}
+
 async fn run_tauri_app() -> Result<()> {
     let builder_result = tauri::Builder::default()
         .plugin(tauri_plugin_upload::init())
@@ -190,18 +278,47 @@ async fn run_tauri_app() -> Result<()> {
             commands::get_checksum_of_files,
             commands::extract_7z,
             commands::copy_files_to_gtasa,
+            favorites::take_pending_favorites,
+            favorites::sync_favorites,
             query::query_server,
+            batch_query::query_servers,
+            master_list::get_master_list,
+            lan::scan_lan,
+            ping::ping_server,
+            rcon::send_rcon_command,
+            server_history::get_server_history,
+            server_history::clear_server_history,
+            server_watch::watch_servers,
+            server_watch::unwatch_servers,
+            settings::get_launcher_settings,
+            settings::set_launcher_settings,
+            schemes::get_scheme_handlers,
+            schemes::set_scheme_claimed,
+            schemes::unregister_schemes,
+            uri::parse_server_uri,
+            link_policy::check_server_link,
+            link_policy::get_link_policy,
+            link_policy::set_link_policy,
             ipc::send_message_to_game
         ])
-        .run(tauri::generate_context!());
+        .build(tauri::generate_context!());
 
-    match builder_result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(LauncherError::InternalError(format!(
-            "Tauri initialization failed: {}",
-            e
-        ))),
-    }
+    let app = match builder_result {
+        Ok(app) => app,
+        Err(e) => {
+            return Err(LauncherError::InternalError(format!(
+                "Tauri initialization failed: {}",
+                e
+            )))
+        }
+    };
+
+    app.run(|_, event| {
+        if let tauri::RunEvent::Exit = event {
+            server_history::flush();
+        }
+    });
+    Ok(())
 }
 
 fn setup_tauri_app(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Error>> {
@@ -219,35 +336,114 @@ fn setup_tauri_app(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std:
         })?;
     }
 
-    #[cfg(windows)]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     setup_deeplinks(handle.clone())?;
 
+    server_history::start();
+    server_watch::start(handle.clone());
     ipc::init_ipc(handle);
     Ok(())
 }
 
-#[cfg(windows)]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 fn setup_deeplinks(
     handle: tauri::AppHandle,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    let handle_omp = handle.clone();
-    let handle_samp = handle.clone();
+    let claimed = settings::load().claimed_schemes;
+
+    for scheme in schemes::SCHEMES {
+        let handle = handle.clone();
+        let handler = move |handoff: deeplink::Handoff| {
+            info!(
+                "Received {} handoff (version {}): {:?}",
+                scheme.to_uppercase(),
+                handoff.version,
+                handoff.intent
+            );
+            handle_handoff(handle.clone(), handoff)
+        };
+
+        // leave schemes the user gave to another client alone
+        if claimed.iter().any(|c| c == scheme) {
+            deeplink::register(scheme, handler)?;
+        } else {
+            deeplink::listen(handler)?;
+        }
+    }
 
-    deeplink::register(DEEPLINK_SCHEME_OMP, move |request| {
-        info!("Received OMP deeplink: {}", request);
-        if let Ok(mut uri_value) = URI_SCHEME_VALUE.lock() {
-            *uri_value = request.clone();
+    Ok(())
+}
+
+/// Carries out what another instance handed off. The error is sent back to
+/// it as the reason for rejecting the request.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn handle_handoff(
+    handle: tauri::AppHandle,
+    handoff: deeplink::Handoff,
+) -> std::result::Result<(), String> {
+    match handoff.intent {
+        deeplink::Intent::OpenUri { uri } => {
+            let check = link_policy::admit(&uri);
+            if check.verdict == LinkVerdict::Block {
+                return Err(check.reason.unwrap_or_default());
+            }
+            handle_deeplink(handle, check);
+            Ok(())
+        }
+        deeplink::Intent::Launch => {
+            let args = CliArgs::parse_args_default::<String>(handoff.argv.get(1..).unwrap_or(&[]))
+                .map_err(|e| e.to_string())?;
+            let mut launch = args.launch_args();
+            // a relative game path is relative to the other instance
+            if let (Some(gamepath), Some(cwd)) = (&launch.gamepath, &handoff.cwd) {
+                launch.gamepath = Some(
+                    std::path::Path::new(cwd)
+                        .join(gamepath)
+                        .display()
+                        .to_string(),
+                );
+            }
+            launch.validate().map_err(|e| e.to_string())?;
+
+            ipc::ensure_listening();
+            // ack right away, the other instance only waits `ACK_TIMEOUT` and
+            // launching can take longer
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = cli::launch(&launch).await {
+                    error!("Launch handed off by another instance failed: {}", e);
+                    focus_main_window(&handle);
+                    let _ = handle.emit_all("handoff-launch-failed", e.to_string());
+                }
+            });
+            Ok(())
+        }
+        deeplink::Intent::Focus => {
+            focus_main_window(&handle);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn focus_main_window(handle: &tauri::AppHandle) {
+    if let Some(window) = handle.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn handle_deeplink(handle: tauri::AppHandle, check: LinkCheck) {
+    tauri::async_runtime::spawn(async move {
+        if launch_link_directly(&check).await {
+            return;
         }
-        let _ = handle_omp.emit_all("scheme-request-received", &request);
-    })?;
 
-    deeplink::register(DEEPLINK_SCHEME_SAMP, move |request| {
-        info!("Received SAMP deeplink: {}", request);
         if let Ok(mut uri_value) = URI_SCHEME_VALUE.lock() {
-            *uri_value = request.clone();
+            *uri_value = check.link.clone();
         }
-        let _ = handle_samp.emit_all("scheme-request-received", &request);
-    })?;
-
-    Ok(())
+        focus_main_window(&handle);
+        let _ = handle.emit_all("scheme-request-received", &check);
+    });
 }
@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{constants::*, errors::*, helpers};
+
+/// A server entry as returned by `/servers/full`.
+#[derive(Deserialize)]
+struct RawServer {
+    core: RawServerCore,
+    #[serde(default)]
+    ru: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct RawServerCore {
+    ip: String,
+    #[serde(default)]
+    hn: String,
+    #[serde(default)]
+    gm: String,
+    #[serde(default)]
+    la: String,
+    #[serde(default)]
+    pa: bool,
+    #[serde(default)]
+    pc: u32,
+    #[serde(default)]
+    pm: u32,
+    #[serde(default)]
+    vn: String,
+    #[serde(default)]
+    omp: bool,
+    #[serde(default)]
+    pr: bool,
+}
+
+/// A validated master list entry, named like the frontend's `Server`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListedServer {
+    pub ip: String,
+    pub port: i32,
+    pub hostname: String,
+    pub game_mode: String,
+    pub language: String,
+    pub has_password: bool,
+    pub player_count: u16,
+    pub max_players: u16,
+    pub version: String,
+    pub using_omp: bool,
+    pub partner: bool,
+    pub rules: HashMap<String, String>,
+}
+
+/// The last list fetched successfully from `base_url`, stored in the local
+/// data dir.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    base_url: String,
+    etag: Option<String>,
+    /// Unix time in seconds of the last successful fetch.
+    fetched_at: u64,
+    servers: Vec<ListedServer>,
+}
+
+#[derive(Serialize)]
+pub struct MasterList {
+    pub servers: Vec<ListedServer>,
+    pub fetched_at: u64,
+    /// `true` if the master list could not be reached and the servers come
+    /// from the last snapshot.
+    pub stale: bool,
+}
+
+enum FetchResult {
+    NotModified,
+    Modified {
+        servers: Vec<ListedServer>,
+        etag: Option<String>,
+    },
+}
+
+fn truncate(value: &str, max: u32) -> String {
+    value.trim().chars().take(max as usize).collect()
+}
+
+/// Splits `host:port`, `[ipv6]:port` or a bare host or IPv6 address into host
+/// and port. IPv6 hosts are returned without brackets.
+fn split_address(address: &str) -> Option<(String, u16)> {
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return Some((addr.ip().to_string(), addr.port()));
+    }
+
+    let bare = address.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Some((ip.to_string(), DEFAULT_SERVER_PORT));
+    }
+
+    match address.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None => Some((address.to_string(), DEFAULT_SERVER_PORT)),
+    }
+}
+
+/// Turns a raw entry into a [`ListedServer`], `None` if its address is unusable.
+fn normalize(raw: RawServer) -> Option<ListedServer> {
+    let (host, port) = split_address(raw.core.ip.trim())?;
+
+    if host.is_empty() || port == 0 {
+        return None;
+    }
+
+    let max_players = raw.core.pm.min(MAX_PLAYER_COUNT as u32) as u16;
+    let rules = raw
+        .ru
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            (name, value)
+        })
+        .collect();
+
+    Some(ListedServer {
+        ip: host,
+        port: port as i32,
+        hostname: truncate(&raw.core.hn, MAX_HOSTNAME_LENGTH),
+        game_mode: truncate(&raw.core.gm, MAX_GAMEMODE_LENGTH),
+        language: truncate(&raw.core.la, MAX_LANGUAGE_LENGTH),
+        has_password: raw.core.pa,
+        player_count: raw.core.pc.min(max_players as u32) as u16,
+        max_players,
+        version: raw.core.vn.trim().to_string(),
+        using_omp: raw.core.omp,
+        partner: raw.core.pr,
+        rules,
+    })
+}
+
+async fn fetch(base_url: &str, etag: Option<&str>) -> Result<FetchResult> {
+    let url = format!("{}/servers/full", base_url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(MASTER_LIST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| LauncherError::Network(format!("Failed to create HTTP client: {}", e)))?;
+
+    let mut request = client.get(&url);
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+
+    let response = request.send().await.map_err(|e| {
+        if e.is_timeout() {
+            LauncherError::Timeout(format!("Master list request to {} timed out", url))
+        } else {
+            LauncherError::Network(format!("Failed to fetch master list: {}", e))
+        }
+    })?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(FetchResult::NotModified);
+    }
+
+    if !response.status().is_success() {
+        return Err(LauncherError::Network(format!(
+            "Master list responded with {}",
+            response.status()
+        )));
+    }
+
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let raw: Vec<RawServer> = response
+        .json()
+        .await
+        .map_err(|e| LauncherError::Parse(format!("Invalid master list response: {}", e)))?;
+
+    let total = raw.len();
+    let mut seen = HashSet::new();
+    let servers: Vec<ListedServer> = raw
+        .into_iter()
+        .filter_map(normalize)
+        .filter(|server| seen.insert((server.ip.to_lowercase(), server.port)))
+        .collect();
+
+    if servers.len() < total {
+        log::debug!(
+            "Dropped {} invalid or duplicate master list entries",
+            total - servers.len()
+        );
+    }
+
+    Ok(FetchResult::Modified { servers, etag })
+}
+
+/// Each base URL gets its own snapshot, so switching lists never serves one
+/// list's servers or ETag for another.
+fn snapshot_path(dir: &Path, base_url: &str) -> PathBuf {
+    let key: String = base_url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    dir.join(format!("{}_{}.json", MASTER_LIST_SNAPSHOT_PREFIX, key))
+}
+
+fn read_snapshot(dir: &Path, base_url: &str) -> Option<Snapshot> {
+    let data = fs::read(snapshot_path(dir, base_url)).ok()?;
+    match serde_json::from_slice::<Snapshot>(&data) {
+        // different URLs can map to the same file name
+        Ok(snapshot) if snapshot.base_url != base_url => None,
+        Ok(snapshot) => Some(snapshot),
+        Err(e) => {
+            log::warn!("Ignoring unreadable master list snapshot: {}", e);
+            None
+        }
+    }
+}
+
+fn write_snapshot(dir: &Path, snapshot: &Snapshot) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = snapshot_path(dir, &snapshot.base_url);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_vec(snapshot)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Fetches the master list from `base_url`, keeping a snapshot in `dir`.
+/// If the list cannot be fetched, the last snapshot is served instead.
+pub async fn load(base_url: &str, dir: Option<&Path>) -> Result<MasterList> {
+    let base_url = base_url.trim_end_matches('/');
+    let snapshot = dir.and_then(|dir| read_snapshot(dir, base_url));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    let fetched = fetch(base_url, snapshot.as_ref().and_then(|s| s.etag.as_deref())).await;
+
+    let snapshot = match (fetched, snapshot) {
+        (Ok(FetchResult::Modified { servers, etag }), _) => Snapshot {
+            base_url: base_url.to_string(),
+            etag,
+            fetched_at: now,
+            servers,
+        },
+        (Ok(FetchResult::NotModified), Some(snapshot)) => Snapshot {
+            fetched_at: now,
+            ..snapshot
+        },
+        (Ok(FetchResult::NotModified), None) => {
+            return Err(LauncherError::Network(
+                "Master list not modified, but there is no snapshot".to_string(),
+            ));
+        }
+        (Err(e), Some(snapshot)) => {
+            log::warn!("{}, serving the snapshot from {}", e, snapshot.fetched_at);
+            return Ok(MasterList {
+                servers: snapshot.servers,
+                fetched_at: snapshot.fetched_at,
+                stale: true,
+            });
+        }
+        (Err(e), None) => return Err(e),
+    };
+
+    if let Some(dir) = dir {
+        if let Err(e) = write_snapshot(dir, &snapshot) {
+            log::warn!("Failed to store master list snapshot: {}", e);
+        }
+    }
+
+    Ok(MasterList {
+        servers: snapshot.servers,
+        fetched_at: snapshot.fetched_at,
+        stale: false,
+    })
+}
+
+#[tauri::command]
+pub async fn get_master_list(base_url: Option<String>) -> Result<MasterList> {
+    let base_url = base_url.unwrap_or_else(|| MASTER_LIST_BASE_URL.to_string());
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(LauncherError::InvalidInput(format!(
+            "Invalid master list URL: {}",
+            base_url
+        )));
+    }
+
+    let dir = helpers::local_data_dir().ok();
+    load(&base_url, dir.as_deref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    const LIST: &str = r#"[
+        {"core": {"ip": "1.2.3.4:7777", "hn": "  First  ", "pc": 5, "pm": 50},
+         "ru": {"weather": "10", "worldtime": 12}},
+        {"core": {"ip": "1.2.3.4:7777", "hn": "Duplicate"}},
+        {"core": {"ip": "[2001:db8::1]:7778", "hn": "IPv6"}},
+        {"core": {"ip": "bad:port"}}
+    ]"#;
+
+    /// Answers one request per response in `responses`, then stops listening.
+    /// Resolves to the `If-None-Match` header of each request.
+    async fn stand_in(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<Option<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut etags = Vec::new();
+            for status in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let amt = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..amt]).to_string();
+                etags.push(request.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("if-none-match")
+                        .then(|| value.trim().to_string())
+                }));
+
+                let body = if status.starts_with("200") { LIST } else { "" };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nETag: \"v1\"\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            etags
+        });
+        (base_url, server)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "omp-launcher-master-list-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn splits_addresses() {
+        let split = |address| split_address(address).unwrap();
+        assert_eq!(split("1.2.3.4:7778"), ("1.2.3.4".to_string(), 7778));
+        assert_eq!(split("example.com"), ("example.com".to_string(), 7777));
+        assert_eq!(
+            split("[2001:db8::1]:7778"),
+            ("2001:db8::1".to_string(), 7778)
+        );
+        assert_eq!(split("2001:db8::1"), ("2001:db8::1".to_string(), 7777));
+        assert_eq!(split("[2001:db8::1]"), ("2001:db8::1".to_string(), 7777));
+        assert!(split_address("example.com:port").is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_snapshot_when_not_modified_or_unreachable() {
+        let dir = temp_dir("snapshot");
+        let (base_url, server) = stand_in(vec!["200 OK", "304 Not Modified"]).await;
+
+        let list = load(&base_url, Some(&dir)).await.unwrap();
+        assert!(!list.stale);
+        let hosts: Vec<_> = list
+            .servers
+            .iter()
+            .map(|s| (s.ip.as_str(), s.port))
+            .collect();
+        assert_eq!(hosts, [("1.2.3.4", 7777), ("2001:db8::1", 7778)]);
+        assert_eq!(list.servers[0].hostname, "First");
+        assert_eq!(list.servers[0].rules["worldtime"], "12");
+
+        let list = load(&base_url, Some(&dir)).await.unwrap();
+        assert!(!list.stale);
+        assert_eq!(list.servers.len(), 2);
+        assert_eq!(server.await.unwrap(), [None, Some("\"v1\"".to_string())]);
+
+        // the stand-in has stopped listening
+        let list = load(&base_url, Some(&dir)).await.unwrap();
+        assert!(list.stale);
+        assert_eq!(list.servers.len(), 2);
+        assert!(load(&base_url, None).await.is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_snapshot_per_base_url() {
+        let dir = temp_dir("per-url");
+        let (first, server) = stand_in(vec!["200 OK"]).await;
+        load(&first, Some(&dir)).await.unwrap();
+        server.await.unwrap();
+
+        let (second, server) = stand_in(vec!["200 OK"]).await;
+        load(&second, Some(&dir)).await.unwrap();
+        assert_eq!(server.await.unwrap(), [None]);
+
+        let (third, server) = stand_in(Vec::new()).await;
+        server.await.unwrap();
+        assert!(load(&third, Some(&dir)).await.is_err());
+        assert!(load(&first, Some(&dir)).await.unwrap().stale);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::query::Query;
+use crate::retransmit::QueryBudget;
+use crate::{constants::*, errors::*};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PingStatus {
+    /// Every probe was answered.
+    Online,
+    /// Some probes were lost, but the server answered at least once.
+    Lossy,
+    /// No probe was answered before its timeout.
+    Unreachable,
+    /// The server actively refused the probes, e.g. nothing listens on the port.
+    Down,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PingStats {
+    pub status: PingStatus,
+    pub sent: u32,
+    pub received: u32,
+    pub min_ms: u32,
+    pub avg_ms: u32,
+    pub max_ms: u32,
+    /// Mean difference between consecutive round trip times.
+    pub jitter_ms: u32,
+    /// Percentage of probes that were lost, from 0 to 100.
+    pub loss: f32,
+}
+
+impl PingStats {
+    fn from_samples(sent: u32, samples: &[Duration], refused: bool) -> Self {
+        let received = samples.len() as u32;
+        let status = if received == sent {
+            PingStatus::Online
+        } else if received > 0 {
+            PingStatus::Lossy
+        } else if refused {
+            PingStatus::Down
+        } else {
+            PingStatus::Unreachable
+        };
+
+        let millis: Vec<u32> = samples.iter().map(|d| d.as_millis() as u32).collect();
+        let (min_ms, avg_ms, max_ms) = if millis.is_empty() {
+            (PING_TIMEOUT, PING_TIMEOUT, PING_TIMEOUT)
+        } else {
+            let sum: u64 = millis.iter().map(|&m| m as u64).sum();
+            (
+                *millis.iter().min().unwrap_or(&0),
+                (sum / millis.len() as u64) as u32,
+                *millis.iter().max().unwrap_or(&0),
+            )
+        };
+
+        let jitter_ms = if millis.len() > 1 {
+            let total: u64 = millis.windows(2).map(|w| w[0].abs_diff(w[1]) as u64).sum();
+            (total / (millis.len() as u64 - 1)) as u32
+        } else {
+            0
+        };
+
+        let loss = if sent == 0 {
+            0.0
+        } else {
+            (sent - received) as f32 * 100.0 / sent as f32
+        };
+
+        Self {
+            status,
+            sent,
+            received,
+            min_ms,
+            avg_ms,
+            max_ms,
+            jitter_ms,
+            loss,
+        }
+    }
+
+    /// The single value shown in the server list, `PING_TIMEOUT` if the server
+    /// never answered.
+    pub fn display_ms(&self) -> u32 {
+        if self.received == 0 {
+            PING_TIMEOUT
+        } else {
+            self.avg_ms
+        }
+    }
+}
+
+/// Runs `count` probes one after another and collects their round trip times.
+/// `probe` has to send a single ping and resolve with its round trip time once
+/// the echo carrying the same token came back.
+pub async fn measure<F, Fut>(count: u32, mut probe: F) -> PingStats
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Duration>>,
+{
+    let mut samples = Vec::with_capacity(count as usize);
+    let mut sent = 0;
+    let mut refused = false;
+
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_millis(PING_PROBE_INTERVAL_MS)).await;
+        }
+
+        sent += 1;
+        match probe().await {
+            Ok(rtt) => samples.push(rtt),
+            Err(LauncherError::Timeout(_)) => {}
+            Err(e) => {
+                // anything but a timeout means the server told us it is not
+                // there, more probes will not change that
+                log::debug!("Ping probe failed: {}", e);
+                refused = true;
+                break;
+            }
+        }
+    }
+
+    PingStats::from_samples(sent, &samples, refused)
+}
+
+#[tauri::command]
+pub async fn ping_server(ip: &str, port: i32, probes: Option<u32>) -> Result<PingStats> {
+    let q = Query::new(ip, port).await?;
+    let count = probes
+        .unwrap_or(PING_PROBE_COUNT)
+        .clamp(1, PING_MAX_PROBE_COUNT);
+    let budget = QueryBudget::new(Duration::from_millis(
+        (PING_PROBE_TIMEOUT_MS + PING_PROBE_INTERVAL_MS) * count as u64,
+    ));
+    Ok(measure(count, || q.ping(&budget)).await)
+}
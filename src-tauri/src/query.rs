@@ -1,49 +1,41 @@
 use actix_web::web::Buf;
 use byteorder::{LittleEndian, ReadBytesExt};
 use once_cell::sync::Lazy;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::{Cursor, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{net::Ipv4Addr, time::Duration};
 use tokio::net::{lookup_host, UdpSocket};
 use tokio::time::timeout_at;
 use tokio::time::Instant;
 
-use crate::{constants::*, errors::*, helpers};
-
-#[derive(Clone, PartialEq)]
-struct QueryParams {
-    info: bool,
-    extra_info: bool,
-    players: bool,
-    rules: bool,
-    ping: bool,
-}
-
-struct RateLimitEntry {
-    timestamp: u64,
-    params: QueryParams,
-}
+use crate::ping::{self, PingStats};
+use crate::query_cache::{self, QuerySection, QuerySections};
+use crate::retransmit::{self, QueryBudget};
+use crate::server_history;
+use crate::{constants::*, errors::*, helpers, validation};
 
 static OMP_EXTRA_INFO_LAST_UPDATE_LIST: Lazy<Mutex<HashMap<String, u64>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
-static QUERY_RATE_LIMIT_LIST: Lazy<Mutex<HashMap<String, RateLimitEntry>>> =
+static DETAILED_PLAYERS_UNSUPPORTED_LIST: Lazy<Mutex<HashMap<SocketAddr, Instant>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
 static CACHED_QUERY: Lazy<tokio::sync::Mutex<Option<(Query, String)>>> =
     Lazy::new(|| tokio::sync::Mutex::new(None));
 
 pub struct Query {
-    address: Ipv4Addr,
-    port: i32,
+    target: SocketAddr,
     socket: UdpSocket,
+    outstanding: Mutex<Option<OutstandingRequest>>,
+    dropped: AtomicU64,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct InfoPacket {
     pub password: bool,
     pub players: u16,
@@ -57,6 +49,11 @@ pub struct InfoPacket {
 pub struct Player {
     pub name: String,
     pub score: i32,
+    /// Only known when the server answered the detailed ('d') player query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ping: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -67,142 +64,587 @@ pub struct ExtraInfoPacket {
     pub logo_url: String,
 }
 
+/// A rule as `(name, value)`, serialized as a two element array.
+pub type Rule = (String, String);
+
+/// A parsed response packet, as returned by [`Query::recv`].
+pub enum QueryPacket {
+    Info(InfoPacket),
+    Players(Vec<Player>),
+    Rules(Vec<Rule>),
+    ExtraInfo(ExtraInfoPacket),
+    Pong,
+    /// A single line of RCON console output.
+    Rcon(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryErrorKind {
+    Timeout,
+    Network,
+    Resolve,
+    InvalidResponse,
+    RateLimited,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueryError {
+    pub kind: QueryErrorKind,
+    pub message: String,
+}
+
+/// The outcome of a single query type, serialized as either `{"ok": ...}` or
+/// `{"error": {"kind": ..., "message": ...}}`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOutcome<T> {
+    Ok(T),
+    Error(QueryError),
+}
+
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct ServerQueryResponse {
-    pub info: Option<String>,
-    pub extra_info: Option<String>,
-    pub players: Option<String>,
-    pub rules: Option<String>,
+    pub info: Option<QueryOutcome<InfoPacket>>,
+    pub extra_info: Option<QueryOutcome<ExtraInfoPacket>>,
+    pub players: Option<QueryOutcome<Vec<Player>>>,
+    pub rules: Option<QueryOutcome<Vec<Rule>>>,
     pub ping: Option<u32>,
+    pub ping_stats: Option<PingStats>,
+    /// Sections answered from the cache because the server was queried for
+    /// them less than `QUERY_RATE_LIMIT_MS` ago.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stale: Vec<QuerySection>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Default)]
-pub struct ErrorResponse {
-    pub info: String,
-    pub error: bool,
+impl QueryPacket {
+    pub fn into_info(self) -> Result<InfoPacket> {
+        match self {
+            QueryPacket::Info(info) => Ok(info),
+            _ => Err(Self::unexpected()),
+        }
+    }
+
+    pub fn into_players(self) -> Result<Vec<Player>> {
+        match self {
+            QueryPacket::Players(players) => Ok(players),
+            _ => Err(Self::unexpected()),
+        }
+    }
+
+    pub fn into_rules(self) -> Result<Vec<Rule>> {
+        match self {
+            QueryPacket::Rules(rules) => Ok(rules),
+            _ => Err(Self::unexpected()),
+        }
+    }
+
+    pub fn into_extra_info(self) -> Result<ExtraInfoPacket> {
+        match self {
+            QueryPacket::ExtraInfo(extra_info) => Ok(extra_info),
+            _ => Err(Self::unexpected()),
+        }
+    }
+
+    pub fn into_rcon_line(self) -> Result<String> {
+        match self {
+            QueryPacket::Rcon(line) => Ok(line),
+            _ => Err(Self::unexpected()),
+        }
+    }
+
+    fn unexpected() -> LauncherError {
+        LauncherError::Parse("Unexpected response packet type".to_string())
+    }
 }
 
-impl Query {
-    pub async fn new(addr: &str, port: i32) -> Result<Self> {
-        let regex = Regex::new(r"^(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$")
-            .map_err(|e| LauncherError::Parse(format!("Invalid regex pattern: {}", e)))?;
+impl QueryError {
+    pub fn new(kind: QueryErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<LauncherError> for QueryError {
+    fn from(err: LauncherError) -> Self {
+        Self::from(&err)
+    }
+}
 
-        let address = match regex.captures(addr) {
-            Some(_) => {
-                // it's valid ipv4, move on
-                addr.to_string()
+impl From<&LauncherError> for QueryError {
+    fn from(err: &LauncherError) -> Self {
+        let kind = match err {
+            LauncherError::Timeout(_) => QueryErrorKind::Timeout,
+            LauncherError::Network(_) | LauncherError::Io(_) => QueryErrorKind::Network,
+            LauncherError::NotFound(_) => QueryErrorKind::Resolve,
+            LauncherError::Parse(_) | LauncherError::InvalidInput(_) => {
+                QueryErrorKind::InvalidResponse
             }
-            None => {
-                let socket_addresses = lookup_host(format!("{}:{}", addr, port)).await;
-                match socket_addresses {
-                    Ok(s) => {
-                        let mut ipv4 = "".to_string();
-                        for socket_address in s {
-                            if socket_address.is_ipv4() {
-                                // hostname is resolved to ipv4:port, lets split it by ":" and get ipv4 only
-                                let ip_port = socket_address.to_string();
-                                let vec: Vec<&str> = ip_port.split(':').collect();
-                                if !vec.is_empty() {
-                                    ipv4 = vec[0].to_string();
-                                    break;
-                                }
-                            }
-                        }
-                        if ipv4.is_empty() {
-                            return Err(LauncherError::NotFound(
-                                "No IPv4 address found for hostname".to_string(),
-                            ));
-                        }
-                        ipv4
-                    }
-                    Err(e) => {
-                        return Err(LauncherError::Network(format!(
-                            "Failed to resolve hostname: {}",
-                            e
-                        )));
-                    }
+            _ => QueryErrorKind::Internal,
+        };
+
+        Self::new(kind, err.to_string())
+    }
+}
+
+impl<T> From<Result<T>> for QueryOutcome<T> {
+    fn from(result: Result<T>) -> Self {
+        match result {
+            Ok(value) => QueryOutcome::Ok(value),
+            Err(e) => QueryOutcome::Error(e.into()),
+        }
+    }
+}
+
+/// Resolves `addr` to the addresses worth trying, IPv4 ones first. `addr`
+/// may be a hostname, an IPv4 literal or an IPv6 literal with or without
+/// brackets.
+pub async fn resolve(addr: &str, port: i32) -> Result<Vec<SocketAddr>> {
+    let port = validation::validate_port(port)?;
+    let host = addr
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(addr);
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let mut addresses: Vec<SocketAddr> = lookup_host((host, port))
+        .await
+        .map_err(|e| LauncherError::NotFound(format!("Failed to resolve hostname: {}", e)))?
+        .collect();
+
+    // stable sort, keeps the resolver's order within each family
+    addresses.sort_by_key(SocketAddr::is_ipv6);
+    addresses.dedup();
+
+    if addresses.is_empty() {
+        return Err(LauncherError::NotFound(
+            "No address found for hostname".to_string(),
+        ));
+    }
+    Ok(addresses)
+}
+
+/// A request that has been sent and is waiting for its reply. Every datagram
+/// received is checked against it, so stale or forged replies are dropped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OutstandingRequest {
+    pub target: SocketAddr,
+    pub query_type: char,
+    pub token: Option<[u8; 4]>,
+}
+
+impl OutstandingRequest {
+    pub fn new(target: SocketAddr, query_type: char) -> Self {
+        let token = if query_type == QUERY_TYPE_PING {
+            Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| (duration.as_nanos() as u32).to_le_bytes())
+                    .unwrap_or([b'S', b'A', b'M', b'P']),
+            )
+        } else {
+            None
+        };
+
+        Self {
+            target,
+            query_type,
+            token,
+        }
+    }
+
+    /// Builds the request packet to send to the server.
+    pub fn packet(&self) -> Vec<u8> {
+        let mut packet = self.header().to_vec();
+        if let Some(token) = self.token {
+            packet.extend_from_slice(&token);
+        }
+        packet
+    }
+
+    /// The 11 byte header every reply has to echo back. It only has room for
+    /// an IPv4 address, so IPv6 targets send their mapped IPv4 address if they
+    /// have one and zeros otherwise. Servers echo the bytes either way.
+    fn header(&self) -> [u8; QUERY_HEADER_LENGTH] {
+        let octets = match self.target.ip() {
+            IpAddr::V4(ip) => ip.octets(),
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or([0; 4], |v4| v4.octets()),
+        };
+        let port = self.target.port().to_le_bytes();
+        let mut header = [0u8; QUERY_HEADER_LENGTH];
+        header[..4].copy_from_slice(SAMP_PACKET_HEADER);
+        header[4..8].copy_from_slice(&octets);
+        header[8..10].copy_from_slice(&port);
+        header[10] = self.query_type as u8;
+        header
+    }
+
+    /// Checks that `datagram` is the reply to this request: the `SAMP` magic,
+    /// the echoed address and port, the opcode and, for pings, the token.
+    pub fn matches(&self, datagram: &[u8]) -> bool {
+        if datagram.len() < QUERY_HEADER_LENGTH || datagram[..QUERY_HEADER_LENGTH] != self.header()
+        {
+            return false;
+        }
+
+        match self.token {
+            Some(token) => {
+                datagram.get(QUERY_HEADER_LENGTH..QUERY_HEADER_LENGTH + 4) == Some(&token)
+            }
+            None => true,
+        }
+    }
+}
+
+/// Checks the open.mp extra info cooldown for `key` and records the attempt
+/// if the cooldown has passed.
+pub fn should_request_extra_info(key: &str, now_secs: u64) -> bool {
+    let mut map = match OMP_EXTRA_INFO_LAST_UPDATE_LIST.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            // Recover from poisoned mutex by getting the data anyway
+            poisoned.into_inner()
+        }
+    };
+
+    match map.get(key) {
+        Some(&last_time) if now_secs - last_time < OMP_EXTRA_INFO_UPDATE_COOLDOWN_SECS => false,
+        _ => {
+            map.insert(key.to_string(), now_secs);
+            true
+        }
+    }
+}
+
+/// Whether `addr` is worth asking for the detailed ('d') player list. Servers
+/// that did not answer it are skipped for `DETAILED_PLAYERS_RETRY_SECS`.
+pub fn detailed_players_supported(addr: SocketAddr) -> bool {
+    let mut map = match DETAILED_PLAYERS_UNSUPPORTED_LIST.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    match map.get(&addr) {
+        Some(since) if since.elapsed() < Duration::from_secs(DETAILED_PLAYERS_RETRY_SECS) => false,
+        Some(_) => {
+            map.remove(&addr);
+            true
+        }
+        None => true,
+    }
+}
+
+pub fn mark_detailed_players_unsupported(addr: SocketAddr) {
+    log::debug!("{} does not answer detailed player queries", addr);
+    let mut map = match DETAILED_PLAYERS_UNSUPPORTED_LIST.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    map.insert(addr, Instant::now());
+}
+
+impl Query {
+    /// Resolves `addr` and connects to the first address that can be reached
+    /// from this machine, trying IPv4 before IPv6.
+    pub async fn new(addr: &str, port: i32) -> Result<Self> {
+        let mut last_error = None;
+        for target in resolve(addr, port).await? {
+            match Self::connect(target).await {
+                Ok(socket) => {
+                    return Ok(Self {
+                        target,
+                        socket,
+                        outstanding: Mutex::new(None),
+                        dropped: AtomicU64::new(0),
+                    })
+                }
+                Err(e) => {
+                    log::debug!("Cannot query {} via {}: {}", addr, target, e);
+                    last_error = Some(e);
                 }
             }
-        };
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            LauncherError::NotFound("No address found for hostname".to_string())
+        }))
+    }
 
-        let parsed_address = address
-            .parse::<Ipv4Addr>()
-            .map_err(|e| LauncherError::InvalidInput(format!("Invalid IP address: {}", e)))?;
+    async fn connect(target: SocketAddr) -> Result<UdpSocket> {
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
 
-        let socket = UdpSocket::bind("0.0.0.0:0")
+        let socket = UdpSocket::bind(local)
             .await
             .map_err(|e| LauncherError::Network(format!("Failed to bind socket: {}", e)))?;
 
         socket
-            .connect(format!("{}:{}", addr, port))
+            .connect(target)
             .await
             .map_err(|e| LauncherError::Network(format!("Failed to connect to server: {}", e)))?;
 
-        let data = Self {
-            address: parsed_address,
-            port,
-            socket,
-        };
-
-        Ok(data)
+        Ok(socket)
     }
 
     pub async fn send(&self, query_type: char) -> Result<usize> {
-        let mut packet: Vec<u8> = Vec::new();
-        packet.extend_from_slice(SAMP_PACKET_HEADER);
-        for i in 0..4 {
-            packet.push(self.address.octets()[i]);
+        let request = OutstandingRequest::new(self.target, query_type);
+        *self.lock_outstanding() = Some(request);
+
+        let amt = self
+            .socket
+            .send(&request.packet())
+            .await
+            .map_err(|e| LauncherError::Network(format!("Failed to send packet: {}", e)))?;
+        Ok(amt)
+    }
+
+    /// Sends `query_type` and waits for the reply, retransmitting with
+    /// exponential backoff until `budget` runs out. The first wait is derived
+    /// from the round trip times measured for this server so far.
+    pub async fn request(&self, query_type: char, budget: &QueryBudget) -> Result<QueryPacket> {
+        let target = self.target();
+        let mut rto = retransmit::initial_rto(target);
+        for attempt in 0..QUERY_MAX_ATTEMPTS {
+            let Some(left) = budget.remaining() else {
+                break;
+            };
+
+            let sent_at = Instant::now();
+            self.send(query_type).await?;
+            match self.recv(rto.min(left)).await {
+                Ok(packet) => {
+                    if attempt == 0 {
+                        retransmit::record_sample(target, sent_at.elapsed());
+                    }
+                    return Ok(packet);
+                }
+                Err(LauncherError::Timeout(_)) => rto = retransmit::backoff(rto),
+                Err(e) => return Err(e),
+            }
         }
-        packet.push((self.port & 0xFF) as u8);
-        packet.push((self.port >> 8 & 0xFF) as u8);
-        packet.push(query_type as u8);
 
-        if query_type == 'p' {
-            let payload = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|duration| (duration.as_nanos() as u32).to_le_bytes())
-                .unwrap_or([b'S', b'A', b'M', b'P']);
-            packet.extend_from_slice(&payload);
+        budget.mark_exhausted();
+        Err(LauncherError::Timeout(format!(
+            "No reply to '{}' query",
+            query_type
+        )))
+    }
+
+    /// Fetches the player list, preferring the detailed ('d') query for IDs and
+    /// pings and falling back to the basic ('c') one if the server refuses it.
+    pub async fn request_players(&self, budget: &QueryBudget) -> Result<Vec<Player>> {
+        let target = self.target();
+        if !detailed_players_supported(target) {
+            return self
+                .request(QUERY_TYPE_PLAYERS, budget)
+                .await
+                .and_then(QueryPacket::into_players);
         }
 
-        let amt = self
-            .socket
+        let detailed_budget = budget.limited(Duration::from_millis(DETAILED_PLAYERS_BUDGET_MS));
+        match self
+            .request(QUERY_TYPE_DETAILED_PLAYERS, &detailed_budget)
+            .await
+            .and_then(QueryPacket::into_players)
+        {
+            Ok(players) => Ok(players),
+            Err(
+                LauncherError::Timeout(_)
+                | LauncherError::Parse(_)
+                | LauncherError::InvalidInput(_),
+            ) => {
+                let players = self
+                    .request(QUERY_TYPE_PLAYERS, budget)
+                    .await
+                    .and_then(QueryPacket::into_players)?;
+                // only now we know the server is alive and just refuses 'd'
+                mark_detailed_players_unsupported(target);
+                Ok(players)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends a single ping probe and waits for the echo carrying its token.
+    /// The round trip is timed from right before the probe is sent. Probes
+    /// are never retransmitted, a lost probe counts as packet loss.
+    pub async fn ping(&self, budget: &QueryBudget) -> Result<Duration> {
+        let wait = budget
+            .remaining()
+            .ok_or_else(|| LauncherError::Timeout("Query budget exhausted".to_string()))?
+            .min(Duration::from_millis(PING_PROBE_TIMEOUT_MS));
+
+        let sent_at = Instant::now();
+        self.send(QUERY_TYPE_PING).await?;
+        match self.recv(wait).await {
+            Ok(QueryPacket::Pong) => {
+                let rtt = sent_at.elapsed();
+                retransmit::record_sample(self.target(), rtt);
+                Ok(rtt)
+            }
+            Ok(_) => Err(LauncherError::Parse(
+                "Unexpected response packet type".to_string(),
+            )),
+            Err(LauncherError::Timeout(_)) => {
+                Err(LauncherError::Timeout("Ping probe lost".to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Runs `command` on the server's RCON console and collects the output.
+    /// A reply can span any number of datagrams with no end marker, so lines
+    /// are read until the server has been quiet for `RCON_QUIET_PERIOD_MS`.
+    /// `on_line` is called for every line as it arrives. Commands are never
+    /// retransmitted, running `gmx` twice is worse than losing its output.
+    pub async fn rcon<F>(
+        &self,
+        password: &str,
+        command: &str,
+        mut on_line: F,
+    ) -> Result<Vec<String>>
+    where
+        F: FnMut(&str),
+    {
+        let request = OutstandingRequest::new(self.target, QUERY_TYPE_RCON);
+        let mut packet = request.packet();
+        for field in [password, command] {
+            let len = u16::try_from(field.len()).map_err(|_| {
+                LauncherError::InvalidInput("RCON password or command too long".to_string())
+            })?;
+            packet.extend_from_slice(&len.to_le_bytes());
+            packet.extend_from_slice(field.as_bytes());
+        }
+
+        *self.lock_outstanding() = Some(request);
+        self.socket
             .send(&packet)
             .await
             .map_err(|e| LauncherError::Network(format!("Failed to send packet: {}", e)))?;
-        Ok(amt)
+
+        let give_up = Instant::now() + Duration::from_millis(RCON_MAX_DURATION_MS);
+        let mut deadline = Instant::now() + Duration::from_millis(RCON_REPLY_TIMEOUT_MS);
+        let mut lines = Vec::new();
+        let mut buf = [0; UDP_BUFFER_SIZE];
+        loop {
+            let amt = match timeout_at(deadline.min(give_up), self.socket.recv(&mut buf)).await {
+                Ok(Ok(n)) => n,
+                Ok(Err(e)) => {
+                    *self.lock_outstanding() = None;
+                    return Err(LauncherError::from(e));
+                }
+                // quiet period passed, the server is done talking
+                Err(_) => break,
+            };
+
+            if !request.matches(&buf[..amt]) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            let line = Self::parse_packet(
+                QUERY_TYPE_RCON,
+                Cursor::new(buf[QUERY_HEADER_LENGTH..amt].to_vec()),
+            )
+            .and_then(QueryPacket::into_rcon_line)?;
+            if lines.is_empty() && line == RCON_INVALID_PASSWORD_REPLY {
+                *self.lock_outstanding() = None;
+                return Err(LauncherError::InvalidInput(
+                    "Invalid RCON password".to_string(),
+                ));
+            }
+
+            on_line(&line);
+            lines.push(line);
+            deadline = Instant::now() + Duration::from_millis(RCON_QUIET_PERIOD_MS);
+        }
+
+        *self.lock_outstanding() = None;
+        Ok(lines)
+    }
+
+    pub fn target(&self) -> SocketAddr {
+        self.target
     }
 
-    pub async fn recv(&self) -> Result<String> {
+    /// Waits up to `wait` for the reply to the last request sent. Datagrams
+    /// that do not answer it are dropped and counted, see
+    /// [`Query::dropped_datagrams`].
+    pub async fn recv(&self, wait: Duration) -> Result<QueryPacket> {
+        let request = self.lock_outstanding().ok_or_else(|| {
+            LauncherError::InvalidInput("No outstanding query request".to_string())
+        })?;
+
+        let deadline = Instant::now() + wait;
         let mut buf = [0; UDP_BUFFER_SIZE];
-        let amt = match timeout_at(
-            Instant::now() + Duration::from_secs(QUERY_TIMEOUT_SECS),
-            self.socket.recv(&mut buf),
-        )
-        .await
-        .map_err(|_| LauncherError::Network("Query timeout".to_string()))?
-        {
-            Ok(n) => n,
-            Err(e) => return Err(LauncherError::from(e)),
-        };
+        loop {
+            let amt = match timeout_at(deadline, self.socket.recv(&mut buf))
+                .await
+                .map_err(|_| LauncherError::Timeout("Query timeout".to_string()))?
+            {
+                Ok(n) => n,
+                Err(e) => return Err(LauncherError::from(e)),
+            };
+
+            if !request.matches(&buf[..amt]) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                log::debug!(
+                    "Dropped {} byte datagram not matching '{}' query to {}",
+                    amt,
+                    request.query_type,
+                    self.target
+                );
+                continue;
+            }
+
+            *self.lock_outstanding() = None;
+            return Self::parse_packet(
+                request.query_type,
+                Cursor::new(buf[QUERY_HEADER_LENGTH..amt].to_vec()),
+            );
+        }
+    }
+
+    /// Number of datagrams received that did not match the outstanding request.
+    pub fn dropped_datagrams(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
 
-        if amt == 0 {
-            return Err(LauncherError::Network("No data received".to_string()));
+    fn lock_outstanding(&self) -> std::sync::MutexGuard<'_, Option<OutstandingRequest>> {
+        match self.outstanding.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
         }
+    }
 
-        let query_type = buf[10] as char;
-        let packet = Cursor::new(buf[11..amt].to_vec());
+    /// Parses the body of a response packet (everything after the opcode).
+    pub fn parse_packet(query_type: char, packet: Cursor<Vec<u8>>) -> Result<QueryPacket> {
         match query_type {
-            QUERY_TYPE_INFO => self.build_info_packet(packet),
-            QUERY_TYPE_PLAYERS => self.build_players_packet(packet),
-            QUERY_TYPE_RULES => self.build_rules_packet(packet),
-            QUERY_TYPE_EXTRA_INFO => self.build_extra_info_packet(packet),
-            QUERY_TYPE_PING => Ok(String::from("pong")),
+            QUERY_TYPE_INFO => Self::build_info_packet(packet).map(QueryPacket::Info),
+            QUERY_TYPE_PLAYERS => Self::build_players_packet(packet).map(QueryPacket::Players),
+            QUERY_TYPE_DETAILED_PLAYERS => {
+                Self::build_detailed_players_packet(packet).map(QueryPacket::Players)
+            }
+            QUERY_TYPE_RULES => Self::build_rules_packet(packet).map(QueryPacket::Rules),
+            QUERY_TYPE_EXTRA_INFO => {
+                Self::build_extra_info_packet(packet).map(QueryPacket::ExtraInfo)
+            }
+            QUERY_TYPE_PING => Ok(QueryPacket::Pong),
+            QUERY_TYPE_RCON => Self::build_rcon_packet(packet).map(QueryPacket::Rcon),
             _ => Err(LauncherError::Network("Unknown query type".to_string())),
         }
     }
 
-    fn build_info_packet(&self, mut packet: Cursor<Vec<u8>>) -> Result<String> {
+    fn build_info_packet(mut packet: Cursor<Vec<u8>>) -> Result<InfoPacket> {
         let password = packet
             .read_i8()
             .map_err(|e| LauncherError::Parse(format!("Failed to read password flag: {}", e)))?
@@ -263,10 +705,10 @@ impl Query {
             .map_err(|e| LauncherError::Parse(format!("Failed to read language: {}", e)))?;
         data.language = helpers::decode_buffer(language_buf).0;
 
-        serde_json::to_string(&data).map_err(|e| LauncherError::SerdeJson(e).into())
+        Ok(data)
     }
 
-    fn build_extra_info_packet(&self, mut packet: Cursor<Vec<u8>>) -> Result<String> {
+    fn build_extra_info_packet(mut packet: Cursor<Vec<u8>>) -> Result<ExtraInfoPacket> {
         let mut data = ExtraInfoPacket::default();
 
         let discord_link_len = packet.read_u32::<LittleEndian>().map_err(|e| {
@@ -329,10 +771,10 @@ impl Query {
             }
         }
 
-        serde_json::to_string(&data).map_err(|e| LauncherError::SerdeJson(e).into())
+        Ok(data)
     }
 
-    fn build_players_packet(&self, mut packet: Cursor<Vec<u8>>) -> Result<String> {
+    fn build_players_packet(mut packet: Cursor<Vec<u8>>) -> Result<Vec<Player>> {
         let player_count = packet
             .read_u16::<LittleEndian>()
             .map_err(|e| LauncherError::Parse(format!("Failed to read player count: {}", e)))?;
@@ -375,10 +817,80 @@ impl Query {
             })?;
         }
 
-        serde_json::to_string(&players).map_err(|e| LauncherError::SerdeJson(e).into())
+        Ok(players)
     }
 
-    fn build_rules_packet(&self, mut packet: Cursor<Vec<u8>>) -> Result<String> {
+    fn build_detailed_players_packet(mut packet: Cursor<Vec<u8>>) -> Result<Vec<Player>> {
+        let player_count = packet
+            .read_u16::<LittleEndian>()
+            .map_err(|e| LauncherError::Parse(format!("Failed to read player count: {}", e)))?;
+
+        if player_count > MAX_PLAYER_COUNT {
+            return Err(LauncherError::InvalidInput(
+                "Player count exceeds maximum".to_string(),
+            ));
+        }
+
+        let mut players = Vec::with_capacity(player_count as usize);
+
+        for i in 0..player_count {
+            let id = packet.read_u8().map_err(|e| {
+                LauncherError::Parse(format!("Failed to read id for player {}: {}", i, e))
+            })?;
+
+            let player_name_len = packet.read_u8().map_err(|e| {
+                LauncherError::Parse(format!(
+                    "Failed to read player name length for player {}: {}",
+                    i, e
+                ))
+            })?;
+            if player_name_len > MAX_PLAYER_NAME_LENGTH {
+                return Err(LauncherError::InvalidInput(
+                    "Player name length exceeds maximum".to_string(),
+                ));
+            }
+
+            let mut player_name_buf = vec![0u8; player_name_len as usize];
+            packet.read_exact(&mut player_name_buf).map_err(|e| {
+                LauncherError::Parse(format!(
+                    "Failed to read player name for player {}: {}",
+                    i, e
+                ))
+            })?;
+
+            let score = packet.read_i32::<LittleEndian>().map_err(|e| {
+                LauncherError::Parse(format!("Failed to read score for player {}: {}", i, e))
+            })?;
+
+            let ping = packet.read_u32::<LittleEndian>().map_err(|e| {
+                LauncherError::Parse(format!("Failed to read ping for player {}: {}", i, e))
+            })?;
+
+            players.push(Player {
+                name: helpers::decode_buffer(player_name_buf).0,
+                score,
+                id: Some(id),
+                ping: Some(ping),
+            });
+        }
+
+        Ok(players)
+    }
+
+    fn build_rcon_packet(mut packet: Cursor<Vec<u8>>) -> Result<String> {
+        let len = packet
+            .read_u16::<LittleEndian>()
+            .map_err(|e| LauncherError::Parse(format!("Failed to read RCON line length: {}", e)))?;
+
+        let mut line_buf = vec![0u8; len as usize];
+        packet
+            .read_exact(&mut line_buf)
+            .map_err(|e| LauncherError::Parse(format!("Failed to read RCON line: {}", e)))?;
+
+        Ok(helpers::decode_buffer(line_buf).0)
+    }
+
+    fn build_rules_packet(mut packet: Cursor<Vec<u8>>) -> Result<Vec<Rule>> {
         let rule_count = packet
             .read_u16::<LittleEndian>()
             .map_err(|e| LauncherError::Parse(format!("Failed to read rule count: {}", e)))?;
@@ -389,11 +901,9 @@ impl Query {
             ));
         }
 
-        let mut rules: Vec<Vec<String>> = Vec::new();
+        let mut rules: Vec<Rule> = Vec::new();
 
         for i in 0..rule_count {
-            let mut rule: Vec<String> = Vec::new();
-
             let rule_name_len = packet.read_u8().map_err(|e| {
                 LauncherError::Parse(format!(
                     "Failed to read rule name length for rule {}: {}",
@@ -410,7 +920,7 @@ impl Query {
             packet.read_exact(&mut rule_name_buf).map_err(|e| {
                 LauncherError::Parse(format!("Failed to read rule name for rule {}: {}", i, e))
             })?;
-            rule.push(helpers::decode_buffer(rule_name_buf).0);
+            let rule_name = helpers::decode_buffer(rule_name_buf).0;
 
             let rule_value_len = packet.read_u8().map_err(|e| {
                 LauncherError::Parse(format!(
@@ -428,16 +938,20 @@ impl Query {
             packet.read_exact(&mut rule_value_buf).map_err(|e| {
                 LauncherError::Parse(format!("Failed to read rule value for rule {}: {}", i, e))
             })?;
-            rule.push(helpers::decode_buffer(rule_value_buf).0);
+            let rule_value = helpers::decode_buffer(rule_value_buf).0;
 
-            rules.push(rule);
+            rules.push((rule_name, rule_value));
         }
 
-        serde_json::to_string(&rules).map_err(|e| LauncherError::SerdeJson(e).into())
+        Ok(rules)
     }
 }
 
+/// Answers a query for one server. Sections fetched less than
+/// `QUERY_RATE_LIMIT_MS` ago are served from the cache and flagged as stale,
+/// and refreshed in the background once the window has passed.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn query_server(
     ip: &str,
     port: i32,
@@ -446,62 +960,75 @@ pub async fn query_server(
     players: bool,
     rules: bool,
     ping: bool,
-) -> Result<String> {
-    let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(duration) => duration.as_millis() as u64,
-        Err(e) => {
-            let error_details = ErrorResponse {
-                error: true,
-                info: format!("System time error: {}", e),
-            };
-            return Ok(serde_json::to_string(&error_details)
-                .unwrap_or_else(|_| r#"{"error":true,"info":"System time error"}"#.to_string()));
-        }
-    };
-
+    budget_ms: Option<u64>,
+) -> Result<ServerQueryResponse> {
     let key = format!("{}:{}", ip, port);
-    let current_params = QueryParams {
+    let wanted = QuerySections {
         info,
         extra_info,
         players,
         rules,
         ping,
     };
+    let budget = QueryBudget::from_millis(budget_ms);
 
-    let should_allow = {
-        let mut map = match QUERY_RATE_LIMIT_LIST.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        match map.get(&key) {
-            Some(entry)
-                if now - entry.timestamp < QUERY_RATE_LIMIT_MS
-                    && entry.params == current_params =>
-            {
-                false
-            }
-            _ => {
-                map.insert(
-                    key.clone(),
-                    RateLimitEntry {
-                        timestamp: now,
-                        params: current_params,
-                    },
-                );
-                true
-            }
-        }
-    };
+    let mut result = fetch_sections(ip, port, &key, wanted, &budget).await?;
 
-    if !should_allow {
-        let error_details = ErrorResponse {
-            error: true,
-            info: "Rate limit exceeded. Please wait before querying this server again.".to_string(),
-        };
-        return Ok(serde_json::to_string(&error_details)
-            .unwrap_or_else(|_| r#"{"error":true,"info":"Rate limit exceeded"}""#.to_string()));
+    let deadline = Instant::now() + budget.remaining().unwrap_or_default();
+    let throttled = query_cache::fill(&key, wanted, &mut result, deadline).await;
+    if !throttled.is_empty() {
+        schedule_refresh(ip.to_string(), port, key, throttled);
     }
 
+    Ok(result)
+}
+
+/// Fetches the sections of `wanted` that are not rate limited and stores
+/// them in the cache.
+async fn fetch_sections(
+    ip: &str,
+    port: i32,
+    key: &str,
+    wanted: QuerySections,
+    budget: &QueryBudget,
+) -> Result<ServerQueryResponse> {
+    let fetch = query_cache::begin(key, wanted);
+    if fetch.sections().is_empty() {
+        return Ok(ServerQueryResponse::default());
+    }
+
+    let fetched = query_sections(ip, port, key, fetch.sections(), budget).await;
+    fetch.finish(&fetched);
+    if let Ok(response) = &fetched {
+        server_history::record(ip, port, response);
+    }
+    fetched
+}
+
+/// Refreshes the throttled `sections` of `key` once its rate limit window has
+/// passed, so the next call gets fresh data.
+fn schedule_refresh(ip: String, port: i32, key: String, sections: QuerySections) {
+    let Some(wait) = query_cache::claim_refresh(&key, sections) else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        let budget = QueryBudget::from_millis(None);
+        if let Err(e) = fetch_sections(&ip, port, &key, sections, &budget).await {
+            log::debug!("Background refresh of {} failed: {}", key, e);
+        }
+        query_cache::release_refresh(&key);
+    });
+}
+
+async fn query_sections(
+    ip: &str,
+    port: i32,
+    key: &str,
+    sections: QuerySections,
+    budget: &QueryBudget,
+) -> Result<ServerQueryResponse> {
     let q = {
         let mut cache = CACHED_QUERY.lock().await;
 
@@ -518,142 +1045,120 @@ pub async fn query_server(
         }
     };
 
-    let mut result = ServerQueryResponse {
-        info: None,
-        extra_info: None,
-        players: None,
-        rules: None,
-        ping: None,
-    };
+    let mut result = ServerQueryResponse::default();
 
-    if info {
-        let _ = q.send('i').await;
-        result.info = Some(match q.recv().await {
-            Ok(p) => format!("{}", p),
-            Err(e) => {
-                let error_details = ErrorResponse {
-                    error: true,
-                    info: e.to_string(),
-                };
-
-                serde_json::to_string(&error_details).unwrap_or_else(|_| {
-                    r#"{"error":true,"info":"Server information query failed"}""#.to_string()
-                })
-            }
-        });
+    if sections.info {
+        result.info = Some(
+            q.request(QUERY_TYPE_INFO, budget)
+                .await
+                .and_then(QueryPacket::into_info)
+                .into(),
+        );
     }
 
-    if players {
-        let _ = q.send('c').await;
-        result.players = Some(match q.recv().await {
-            Ok(p) => format!("{}", p),
-            Err(e) => {
-                let error_details = ErrorResponse {
-                    error: true,
-                    info: e.to_string(),
-                };
+    if sections.players {
+        result.players = Some(q.request_players(budget).await.into());
+    }
 
-                serde_json::to_string(&error_details).unwrap_or_else(|_| {
-                    r#"{"error":true,"info":"Server players query failed"}""#.to_string()
-                })
-            }
-        });
+    if sections.rules {
+        result.rules = Some(
+            q.request(QUERY_TYPE_RULES, budget)
+                .await
+                .and_then(QueryPacket::into_rules)
+                .into(),
+        );
     }
 
-    if rules {
-        let _ = q.send('r').await;
-        result.rules = Some(match q.recv().await {
-            Ok(p) => format!("{}", p),
-            Err(e) => {
-                let error_details = ErrorResponse {
-                    error: true,
-                    info: e.to_string(),
-                };
+    if sections.extra_info {
+        let now_secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
-                serde_json::to_string(&error_details).unwrap_or_else(|_| {
-                    r#"{"error":true,"info":"Server rules query failed"}""#.to_string()
-                })
-            }
-        });
+        if should_request_extra_info(key, now_secs) {
+            result.extra_info = Some(
+                q.request(QUERY_TYPE_EXTRA_INFO, budget)
+                    .await
+                    .and_then(QueryPacket::into_extra_info)
+                    .into(),
+            );
+        }
     }
 
-    if extra_info {
-        let now_secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(duration) => duration.as_secs(),
-            Err(e) => {
-                let error_details = ErrorResponse {
-                    error: true,
-                    info: format!("System time error: {}", e),
-                };
-                return Ok(serde_json::to_string(&error_details).unwrap_or_else(|_| {
-                    r#"{"error":true,"info":"System time error"}"#.to_string()
-                }));
-            }
-        };
+    if sections.ping {
+        let stats = ping::measure(PING_PROBE_COUNT, || q.ping(budget)).await;
+        result.ping = Some(stats.display_ms());
+        result.ping_stats = Some(stats);
+    }
 
-        let key = format!("{}:{}", ip, port);
+    if q.dropped_datagrams() > 0 {
+        log::debug!(
+            "{} stray datagrams dropped so far on query socket for {}",
+            q.dropped_datagrams(),
+            key
+        );
+    }
 
-        let should_request = {
-            let mut map = match OMP_EXTRA_INFO_LAST_UPDATE_LIST.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => {
-                    // Recover from poisoned mutex by getting the data anyway
-                    poisoned.into_inner()
-                }
-            };
+    // Store the query back in cache for next use
+    {
+        let mut cache = CACHED_QUERY.lock().await;
+        *cache = Some((q, key.to_string()));
+    }
 
-            match map.get(&key) {
-                Some(&last_time) if now_secs - last_time < OMP_EXTRA_INFO_UPDATE_COOLDOWN_SECS => {
-                    false
-                }
-                _ => {
-                    map.insert(key.clone(), now_secs);
-                    true
-                }
-            }
-        };
+    Ok(result)
+}
 
-        if should_request {
-            let _ = q.send('o').await;
-            result.extra_info = Some(match q.recv().await {
-                Ok(p) => format!("{}", p),
-                Err(e) => {
-                    let error_details = ErrorResponse {
-                        error: true,
-                        info: e.to_string(),
-                    };
-
-                    serde_json::to_string(&error_details).unwrap_or_else(|_| {
-                        r#"{"error":true,"info":"Server open.mp information query failed"}""#
-                            .to_string()
-                    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request with replies that differ from it in the magic,
+    /// the echoed port, the opcode and the ping token, then with the request
+    /// itself, which is a valid pong.
+    async fn spawn_stand_in() -> SocketAddr {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; UDP_BUFFER_SIZE];
+            loop {
+                let Ok((amt, from)) = server.recv_from(&mut buf).await else {
+                    return;
+                };
+                let request = buf[..amt].to_vec();
+                let mut replies = vec![request.clone(); 4];
+                replies[0][0] = b'X';
+                replies[1][8] ^= 0xff;
+                replies[2][10] = QUERY_TYPE_INFO as u8;
+                replies[3][QUERY_HEADER_LENGTH] ^= 0xff;
+                replies.push(request);
+                for reply in replies {
+                    server.send_to(&reply, from).await.unwrap();
                 }
-            });
-        }
-    }
-
-    if ping {
-        let _ = q.send('p').await;
-        let before = Instant::now();
-        match q.recv().await {
-            Ok(_p) => {
-                result.ping = Some(before.elapsed().as_millis() as u32);
             }
-            Err(_) => {
-                result.ping = Some(PING_TIMEOUT);
-            }
-        }
+        });
+        addr
     }
 
-    // Store the query back in cache for next use
-    {
-        let mut cache = CACHED_QUERY.lock().await;
-        *cache = Some((q, key));
+    #[test]
+    fn matches_only_the_reply_to_the_request() {
+        let target: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let request = OutstandingRequest::new(target, QUERY_TYPE_PING);
+        let reply = request.packet();
+        assert!(request.matches(&reply));
+        assert!(!request.matches(&reply[..QUERY_HEADER_LENGTH]));
+
+        for index in [0, 4, 8, 10, QUERY_HEADER_LENGTH] {
+            let mut forged = reply.clone();
+            forged[index] ^= 0xff;
+            assert!(!request.matches(&forged), "byte {} not checked", index);
+        }
     }
 
-    let ret = serde_json::to_string(&result).unwrap_or_else(|_| {
-        r#"{"error":true,"info":"Information serialization failed"}""#.to_string()
-    });
+    #[tokio::test]
+    async fn request_drops_mismatched_replies() {
+        let addr = spawn_stand_in().await;
+        let query = Query::new("127.0.0.1", addr.port() as i32).await.unwrap();
+        let budget = QueryBudget::new(Duration::from_secs(2));
 
-    Ok(ret)
+        let packet = query.request(QUERY_TYPE_PING, &budget).await.unwrap();
+        assert!(matches!(packet, QueryPacket::Pong));
+        assert_eq!(query.dropped_datagrams(), 4);
+    }
 }
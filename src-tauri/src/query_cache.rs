@@ -0,0 +1,414 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{timeout_at, Instant};
+
+use crate::constants::*;
+use crate::errors::Result;
+use crate::ping::PingStats;
+use crate::query::{
+    ExtraInfoPacket, InfoPacket, Player, QueryError, QueryErrorKind, QueryOutcome, Rule,
+    ServerQueryResponse,
+};
+
+static QUERY_CACHE: Lazy<Mutex<HashMap<String, EndpointCache>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QuerySection {
+    Info,
+    ExtraInfo,
+    Players,
+    Rules,
+    Ping,
+}
+
+impl QuerySection {
+    const ALL: [QuerySection; 5] = [
+        QuerySection::Info,
+        QuerySection::ExtraInfo,
+        QuerySection::Players,
+        QuerySection::Rules,
+        QuerySection::Ping,
+    ];
+}
+
+/// The sections a single query call asks for.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct QuerySections {
+    pub info: bool,
+    pub extra_info: bool,
+    pub players: bool,
+    pub rules: bool,
+    pub ping: bool,
+}
+
+impl QuerySections {
+    pub fn contains(&self, section: QuerySection) -> bool {
+        match section {
+            QuerySection::Info => self.info,
+            QuerySection::ExtraInfo => self.extra_info,
+            QuerySection::Players => self.players,
+            QuerySection::Rules => self.rules,
+            QuerySection::Ping => self.ping,
+        }
+    }
+
+    fn set(&mut self, section: QuerySection, value: bool) {
+        match section {
+            QuerySection::Info => self.info = value,
+            QuerySection::ExtraInfo => self.extra_info = value,
+            QuerySection::Players => self.players = value,
+            QuerySection::Rules => self.rules = value,
+            QuerySection::Ping => self.ping = value,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !QuerySection::ALL.iter().any(|&s| self.contains(s))
+    }
+
+    fn iter(self) -> impl Iterator<Item = QuerySection> {
+        QuerySection::ALL
+            .into_iter()
+            .filter(move |&s| self.contains(s))
+    }
+}
+
+struct Cached<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+impl<T: Clone> Cached<T> {
+    fn fresh(entry: &Option<Self>) -> Option<T> {
+        entry
+            .as_ref()
+            .filter(|c| c.fetched_at.elapsed() < Duration::from_secs(QUERY_CACHE_TTL_SECS))
+            .map(|c| c.value.clone())
+    }
+}
+
+/// What a fetch got, `None` until it is done.
+type FetchOutcome = Option<Arc<std::result::Result<ServerQueryResponse, QueryError>>>;
+
+/// The last good result of every section of one `ip:port`, plus what is
+/// currently being fetched for it.
+struct EndpointCache {
+    info: Option<Cached<InfoPacket>>,
+    extra_info: Option<Cached<ExtraInfoPacket>>,
+    players: Option<Cached<Vec<Player>>>,
+    rules: Option<Cached<Vec<Rule>>>,
+    ping: Option<Cached<PingStats>>,
+    last_attempt: HashMap<QuerySection, Instant>,
+    /// Sections being fetched, with the outcome of their fetch to wait for.
+    in_flight: HashMap<QuerySection, watch::Receiver<FetchOutcome>>,
+    refresh_scheduled: bool,
+}
+
+impl EndpointCache {
+    fn new() -> Self {
+        Self {
+            info: None,
+            extra_info: None,
+            players: None,
+            rules: None,
+            ping: None,
+            last_attempt: HashMap::new(),
+            in_flight: HashMap::new(),
+            refresh_scheduled: false,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        let ttl = Duration::from_secs(QUERY_CACHE_TTL_SECS);
+        self.in_flight.is_empty()
+            && !self.refresh_scheduled
+            && self.last_attempt.values().all(|at| at.elapsed() >= ttl)
+    }
+
+    fn throttled(&self, section: QuerySection) -> bool {
+        self.last_attempt
+            .get(&section)
+            .is_some_and(|at| at.elapsed() < Duration::from_millis(QUERY_RATE_LIMIT_MS))
+    }
+
+    /// Keeps the good parts of `response`.
+    fn store(&mut self, response: &ServerQueryResponse) {
+        let now = Instant::now();
+        if let Some(QueryOutcome::Ok(info)) = &response.info {
+            self.info = Some(Cached {
+                value: info.clone(),
+                fetched_at: now,
+            });
+        }
+        if let Some(QueryOutcome::Ok(extra_info)) = &response.extra_info {
+            self.extra_info = Some(Cached {
+                value: extra_info.clone(),
+                fetched_at: now,
+            });
+        }
+        if let Some(QueryOutcome::Ok(players)) = &response.players {
+            self.players = Some(Cached {
+                value: players.clone(),
+                fetched_at: now,
+            });
+        }
+        if let Some(QueryOutcome::Ok(rules)) = &response.rules {
+            self.rules = Some(Cached {
+                value: rules.clone(),
+                fetched_at: now,
+            });
+        }
+        if let Some(stats) = response.ping_stats.as_ref().filter(|s| s.received > 0) {
+            self.ping = Some(Cached {
+                value: stats.clone(),
+                fetched_at: now,
+            });
+        }
+    }
+}
+
+fn lock_cache() -> std::sync::MutexGuard<'static, HashMap<String, EndpointCache>> {
+    match QUERY_CACHE.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// The sections of one `ip:port` a call is fetching. They stay marked as in
+/// flight until it is dropped, even if the call is cancelled.
+pub struct Fetch {
+    key: String,
+    sections: QuerySections,
+    outcome: watch::Sender<FetchOutcome>,
+}
+
+impl Fetch {
+    pub fn sections(&self) -> QuerySections {
+        self.sections
+    }
+
+    /// Stores the good parts of `fetched` and hands it to the calls waiting
+    /// on these sections.
+    pub fn finish(self, fetched: &Result<ServerQueryResponse>) {
+        if let Ok(response) = fetched {
+            if let Some(entry) = lock_cache().get_mut(&self.key) {
+                entry.store(response);
+            }
+        }
+
+        let outcome = match fetched {
+            Ok(response) => Ok(response.clone()),
+            Err(e) => Err(QueryError::from(e)),
+        };
+        self.outcome.send_replace(Some(Arc::new(outcome)));
+    }
+}
+
+impl Drop for Fetch {
+    fn drop(&mut self) {
+        if let Some(entry) = lock_cache().get_mut(&self.key) {
+            for section in self.sections.iter() {
+                entry.in_flight.remove(&section);
+            }
+        }
+    }
+}
+
+/// Decides which of the `wanted` sections of `key` have to be fetched now and
+/// marks them as in flight. The others were fetched less than
+/// `QUERY_RATE_LIMIT_MS` ago or are being fetched by another call, and are
+/// answered by [`fill`] instead.
+pub fn begin(key: &str, wanted: QuerySections) -> Fetch {
+    let (outcome, waiting) = watch::channel(None);
+    let mut map = lock_cache();
+    map.retain(|_, entry| !entry.is_expired());
+
+    let entry = map
+        .entry(key.to_string())
+        .or_insert_with(EndpointCache::new);
+
+    let now = Instant::now();
+    let mut sections = QuerySections::default();
+    for section in wanted.iter() {
+        if entry.in_flight.contains_key(&section) || entry.throttled(section) {
+            continue;
+        }
+        sections.set(section, true);
+        entry.in_flight.insert(section, waiting.clone());
+        entry.last_attempt.insert(section, now);
+    }
+
+    Fetch {
+        key: key.to_string(),
+        sections,
+        outcome,
+    }
+}
+
+/// Copies `section` of what another call fetched into `response`.
+fn copy_section(
+    section: QuerySection,
+    fetched: &std::result::Result<ServerQueryResponse, QueryError>,
+    response: &mut ServerQueryResponse,
+) {
+    let fetched = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            match section {
+                QuerySection::Info => response.info = Some(QueryOutcome::Error(e.clone())),
+                QuerySection::ExtraInfo => response.extra_info = None,
+                QuerySection::Players => response.players = Some(QueryOutcome::Error(e.clone())),
+                QuerySection::Rules => response.rules = Some(QueryOutcome::Error(e.clone())),
+                QuerySection::Ping => response.ping = Some(PING_TIMEOUT),
+            }
+            return;
+        }
+    };
+
+    match section {
+        QuerySection::Info => response.info = fetched.info.clone(),
+        QuerySection::ExtraInfo => response.extra_info = fetched.extra_info.clone(),
+        QuerySection::Players => response.players = fetched.players.clone(),
+        QuerySection::Rules => response.rules = fetched.rules.clone(),
+        QuerySection::Ping => {
+            response.ping = fetched.ping;
+            response.ping_stats = fetched.ping_stats.clone();
+        }
+    }
+}
+
+/// Fills every `wanted` section missing from `response`. Sections another
+/// call is fetching get its result, if it arrives before `deadline`. The
+/// others get the last good result and are listed in `response.stale`, or a
+/// `RateLimited` error if there is none. Returns the sections that were
+/// throttled, so the caller can refresh them once the limit window passed.
+pub async fn fill(
+    key: &str,
+    wanted: QuerySections,
+    response: &mut ServerQueryResponse,
+    deadline: Instant,
+) -> QuerySections {
+    let mut missing = QuerySections::default();
+    for section in wanted.iter() {
+        let present = match section {
+            QuerySection::Info => response.info.is_some(),
+            QuerySection::ExtraInfo => response.extra_info.is_some(),
+            QuerySection::Players => response.players.is_some(),
+            QuerySection::Rules => response.rules.is_some(),
+            QuerySection::Ping => response.ping_stats.is_some(),
+        };
+        missing.set(section, !present);
+    }
+
+    if missing.is_empty() {
+        return missing;
+    }
+
+    let mut throttled = missing;
+    let in_flight: Vec<_> = match lock_cache().get(key) {
+        Some(entry) => missing
+            .iter()
+            .filter_map(|s| Some((s, entry.in_flight.get(&s)?.clone())))
+            .collect(),
+        None => Vec::new(),
+    };
+    for (section, mut waiting) in in_flight {
+        throttled.set(section, false);
+        // a fetch that was cancelled or is too slow leaves the cache to fall
+        // back on
+        let fetched = match timeout_at(deadline, waiting.wait_for(Option::is_some)).await {
+            Ok(Ok(fetched)) => fetched.clone(),
+            _ => continue,
+        };
+        if let Some(fetched) = fetched {
+            copy_section(section, &fetched, response);
+            missing.set(section, false);
+        }
+    }
+
+    let map = lock_cache();
+    let entry = map.get(key);
+    for section in missing.iter() {
+        let served = match section {
+            QuerySection::Info => {
+                let cached = entry.and_then(|e| Cached::fresh(&e.info));
+                let served = cached.is_some();
+                response.info = Some(cached.map_or_else(rate_limited_outcome, QueryOutcome::Ok));
+                served
+            }
+            QuerySection::ExtraInfo => {
+                // a missing extra info section is not an error, it is only
+                // sent by open.mp servers
+                let cached = entry.and_then(|e| Cached::fresh(&e.extra_info));
+                let served = cached.is_some();
+                response.extra_info = cached.map(QueryOutcome::Ok);
+                served
+            }
+            QuerySection::Players => {
+                let cached = entry.and_then(|e| Cached::fresh(&e.players));
+                let served = cached.is_some();
+                response.players = Some(cached.map_or_else(rate_limited_outcome, QueryOutcome::Ok));
+                served
+            }
+            QuerySection::Rules => {
+                let cached = entry.and_then(|e| Cached::fresh(&e.rules));
+                let served = cached.is_some();
+                response.rules = Some(cached.map_or_else(rate_limited_outcome, QueryOutcome::Ok));
+                served
+            }
+            QuerySection::Ping => {
+                let cached = entry.and_then(|e| Cached::fresh(&e.ping));
+                let served = cached.is_some();
+                response.ping = cached.as_ref().map(PingStats::display_ms);
+                response.ping_stats = cached;
+                served
+            }
+        };
+
+        if served {
+            response.stale.push(section);
+        }
+    }
+
+    throttled
+}
+
+fn rate_limited_outcome<T>() -> QueryOutcome<T> {
+    QueryOutcome::Error(QueryError::new(
+        QueryErrorKind::RateLimited,
+        "Rate limit exceeded. Please wait before querying this server again.",
+    ))
+}
+
+/// Claims the background refresh of `key`. Returns how long to wait until the
+/// rate limit window of `sections` has passed, or `None` if a refresh is
+/// already scheduled.
+pub fn claim_refresh(key: &str, sections: QuerySections) -> Option<Duration> {
+    let mut map = lock_cache();
+    let entry = map.get_mut(key)?;
+    if entry.refresh_scheduled {
+        return None;
+    }
+    entry.refresh_scheduled = true;
+
+    let window = Duration::from_millis(QUERY_RATE_LIMIT_MS);
+    let wait = sections
+        .iter()
+        .filter_map(|s| entry.last_attempt.get(&s))
+        .map(|at| window.saturating_sub(at.elapsed()))
+        .max()
+        .unwrap_or_default();
+    Some(wait)
+}
+
+pub fn release_refresh(key: &str) {
+    if let Some(entry) = lock_cache().get_mut(key) {
+        entry.refresh_scheduled = false;
+    }
+}
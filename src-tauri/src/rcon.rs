@@ -0,0 +1,51 @@
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::errors::*;
+use crate::query::Query;
+
+#[derive(Serialize, Clone)]
+pub struct RconOutput {
+    pub session_id: u32,
+    pub line: String,
+}
+
+/// Runs `command` on the server's RCON console, e.g. `players`, `kick 3` or
+/// `gmx`. Output lines are emitted as `rcon-output` events tagged with
+/// `session_id` while they arrive, and returned together once the server
+/// stops sending.
+#[tauri::command]
+pub async fn send_rcon_command(
+    app_handle: tauri::AppHandle,
+    session_id: u32,
+    ip: &str,
+    port: i32,
+    password: &str,
+    command: &str,
+) -> Result<Vec<String>> {
+    if password.is_empty() {
+        return Err(LauncherError::InvalidInput(
+            "RCON password cannot be empty".to_string(),
+        ));
+    }
+
+    let command = command.trim();
+    if command.is_empty() {
+        return Err(LauncherError::InvalidInput(
+            "RCON command cannot be empty".to_string(),
+        ));
+    }
+
+    let q = Query::new(ip, port).await?;
+    log::info!("Sending RCON command '{}' to {}:{}", command, ip, port);
+    q.rcon(password, command, |line| {
+        let _ = app_handle.emit_all(
+            "rcon-output",
+            RconOutput {
+                session_id,
+                line: line.to_string(),
+            },
+        );
+    })
+    .await
+}
@@ -0,0 +1,125 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::constants::*;
+
+static RTT_ESTIMATES: Lazy<Mutex<HashMap<SocketAddr, RttEstimator>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Smoothed round trip time of a single server, used to derive how long to
+/// wait for a reply before retransmitting (RFC 6298).
+#[derive(Clone, Copy)]
+struct RttEstimator {
+    srtt: f64,
+    rttvar: f64,
+}
+
+impl RttEstimator {
+    fn new(sample: f64) -> Self {
+        Self {
+            srtt: sample,
+            rttvar: sample / 2.0,
+        }
+    }
+
+    fn update(&mut self, sample: f64) {
+        self.rttvar = 0.75 * self.rttvar + 0.25 * (self.srtt - sample).abs();
+        self.srtt = 0.875 * self.srtt + 0.125 * sample;
+    }
+
+    fn rto(&self) -> Duration {
+        let millis = self.srtt + (4.0 * self.rttvar).max(QUERY_RTO_GRANULARITY_MS as f64);
+        Duration::from_millis(millis as u64)
+    }
+}
+
+fn lock_estimates() -> std::sync::MutexGuard<'static, HashMap<SocketAddr, RttEstimator>> {
+    match RTT_ESTIMATES.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// How long to wait for the first reply from `addr` before retransmitting.
+pub fn initial_rto(addr: SocketAddr) -> Duration {
+    let rto = match lock_estimates().get(&addr) {
+        Some(estimate) => estimate.rto(),
+        None => Duration::from_millis(QUERY_INITIAL_RTO_MS),
+    };
+    clamp_rto(rto)
+}
+
+/// Doubles `rto` for the next attempt.
+pub fn backoff(rto: Duration) -> Duration {
+    clamp_rto(rto * 2)
+}
+
+/// Feeds a round trip time into the estimate for `addr`. Only pass samples of
+/// requests that were not retransmitted, otherwise it is unknown which send the
+/// reply belongs to.
+pub fn record_sample(addr: SocketAddr, rtt: Duration) {
+    let sample = rtt.as_secs_f64() * 1000.0;
+    lock_estimates()
+        .entry(addr)
+        .and_modify(|estimate| estimate.update(sample))
+        .or_insert_with(|| RttEstimator::new(sample));
+}
+
+fn clamp_rto(rto: Duration) -> Duration {
+    rto.clamp(
+        Duration::from_millis(QUERY_MIN_RTO_MS),
+        Duration::from_millis(QUERY_MAX_RTO_MS),
+    )
+}
+
+/// Total time one query call may spend on a server, shared by every query type
+/// in that call. Once a server has let a request run out of attempts the budget
+/// is marked exhausted, so the remaining types fail fast instead of waiting for
+/// the same dead server again.
+pub struct QueryBudget {
+    deadline: Instant,
+    exhausted: AtomicBool,
+}
+
+impl QueryBudget {
+    pub fn new(total: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + total,
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn from_millis(total_ms: Option<u64>) -> Self {
+        Self::new(Duration::from_millis(
+            total_ms.unwrap_or(QUERY_DEFAULT_BUDGET_MS),
+        ))
+    }
+
+    /// Time left for the next attempt, `None` once the budget is spent.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+    }
+
+    /// A budget for a single optional request that must not use up more than
+    /// `max` of this one. Running it out does not mark this budget exhausted.
+    pub fn limited(&self, max: Duration) -> Self {
+        Self {
+            deadline: self.deadline.min(Instant::now() + max),
+            exhausted: AtomicBool::new(self.exhausted.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn mark_exhausted(&self) {
+        self.exhausted.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::{constants::*, errors::*, settings};
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+use crate::deeplink;
+
+/// Link schemes the launcher can handle.
+pub const SCHEMES: [&str; 2] = [DEEPLINK_SCHEME_OMP, DEEPLINK_SCHEME_SAMP];
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod deeplink {
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::PathBuf;
+
+    fn unsupported<T>() -> Result<T> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Link handlers are not supported on this platform",
+        ))
+    }
+
+    pub fn claim(_scheme: &str) -> Result<()> {
+        unsupported()
+    }
+
+    pub fn unregister(_scheme: &str) -> Result<()> {
+        unsupported()
+    }
+
+    pub fn handler(_scheme: &str) -> Result<Option<PathBuf>> {
+        unsupported()
+    }
+
+    pub fn executable() -> Result<PathBuf> {
+        unsupported()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SchemeStatus {
+    pub scheme: String,
+    /// Whether the launcher registers itself for the scheme on startup.
+    pub claimed: bool,
+    /// The executable links of the scheme currently open with.
+    pub handler: Option<String>,
+    /// Whether that executable is this launcher.
+    pub is_ours: bool,
+}
+
+fn validate_scheme(scheme: &str) -> Result<&'static str> {
+    SCHEMES
+        .into_iter()
+        .find(|known| known.eq_ignore_ascii_case(scheme))
+        .ok_or_else(|| LauncherError::InvalidInput(format!("Unknown link scheme '{}'", scheme)))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a
+            .to_string_lossy()
+            .eq_ignore_ascii_case(&b.to_string_lossy()),
+    }
+}
+
+pub fn status() -> Result<Vec<SchemeStatus>> {
+    let claimed = settings::load().claimed_schemes;
+    let ours = deeplink::executable()?;
+
+    SCHEMES
+        .iter()
+        .map(|&scheme| {
+            let handler = deeplink::handler(scheme)?;
+            Ok(SchemeStatus {
+                scheme: scheme.to_string(),
+                claimed: claimed.iter().any(|c| c == scheme),
+                is_ours: handler.as_deref().is_some_and(|h| same_file(h, &ours)),
+                handler: handler.map(|h| h.display().to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Sets whether the launcher handles `scheme`, registering it right away or
+/// removing the launcher's registration. A registration of another client is
+/// left alone.
+pub fn set_claimed(scheme: &str, claimed: bool) -> Result<()> {
+    let scheme = validate_scheme(scheme)?;
+
+    let mut settings = settings::load();
+    settings.claimed_schemes.retain(|s| s != scheme);
+    if claimed {
+        settings.claimed_schemes.push(scheme.to_string());
+    }
+    settings::save(&settings)?;
+
+    if claimed {
+        deeplink::claim(scheme)?;
+    } else {
+        deeplink::unregister(scheme)?;
+    }
+    Ok(())
+}
+
+/// Stops claiming the schemes and removes the launcher's registrations, for
+/// uninstalling or switching to another client.
+pub fn unregister_all() -> Result<()> {
+    for scheme in SCHEMES {
+        set_claimed(scheme, false)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_scheme_handlers() -> Result<Vec<SchemeStatus>> {
+    status()
+}
+
+#[tauri::command]
+pub fn set_scheme_claimed(scheme: String, claimed: bool) -> Result<Vec<SchemeStatus>> {
+    set_claimed(&scheme, claimed)?;
+    status()
+}
+
+#[tauri::command]
+pub fn unregister_schemes() -> Result<Vec<SchemeStatus>> {
+    unregister_all()?;
+    status()
+}
@@ -0,0 +1,349 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::query::{QueryOutcome, ServerQueryResponse};
+use crate::{constants::*, errors::*, helpers};
+
+static HISTORY: Lazy<Mutex<HistoryStore>> = Lazy::new(|| Mutex::new(HistoryStore::default()));
+
+/// Aggregated query results of one endpoint over a bucket of time. Recent
+/// samples cover `SERVER_HISTORY_RECENT_BUCKET_SECS`, older ones are merged
+/// into buckets of `SERVER_HISTORY_OLD_BUCKET_SECS`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistorySample {
+    /// Unix time in seconds the bucket starts at.
+    pub time: u64,
+    /// Number of queries merged into this sample.
+    pub samples: u32,
+    /// Number of those queries the server answered.
+    pub online: u32,
+    /// Average ping of the queries that measured one.
+    pub ping: Option<u32>,
+    #[serde(default)]
+    pub ping_samples: u32,
+    /// Average player count while the server was online.
+    pub players: Option<u16>,
+    pub peak_players: Option<u16>,
+}
+
+impl HistorySample {
+    fn new(time: u64, online: bool, ping: Option<u32>, players: Option<u16>) -> Self {
+        Self {
+            time,
+            samples: 1,
+            online: online as u32,
+            ping,
+            ping_samples: ping.is_some() as u32,
+            players,
+            peak_players: players,
+        }
+    }
+
+    fn merge(&mut self, other: &HistorySample) {
+        self.ping = weighted_average(self.ping, self.ping_samples, other.ping, other.ping_samples);
+        self.players = weighted_average(
+            self.players.map(u32::from),
+            self.online,
+            other.players.map(u32::from),
+            other.online,
+        )
+        .map(|players| players as u16);
+        self.peak_players = self.peak_players.max(other.peak_players);
+        self.samples += other.samples;
+        self.online += other.online;
+        self.ping_samples += other.ping_samples;
+    }
+}
+
+fn weighted_average(a: Option<u32>, a_weight: u32, b: Option<u32>, b_weight: u32) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) if a_weight + b_weight > 0 => {
+            let total = a as u64 * a_weight as u64 + b as u64 * b_weight as u64;
+            Some((total / (a_weight + b_weight) as u64) as u32)
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+/// Merges `samples`, sorted by time, into buckets of `bucket_secs`.
+fn downsample(samples: Vec<HistorySample>, bucket_secs: u64) -> Vec<HistorySample> {
+    let mut merged: Vec<HistorySample> = Vec::with_capacity(samples.len());
+    for mut sample in samples {
+        sample.time -= sample.time % bucket_secs;
+        match merged.last_mut() {
+            Some(last) if last.time == sample.time => last.merge(&sample),
+            _ => merged.push(sample),
+        }
+    }
+    merged
+}
+
+/// What is stored in `SERVER_HISTORY_FILE`.
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryFile {
+    endpoints: BTreeMap<String, Vec<HistorySample>>,
+}
+
+#[derive(Default)]
+struct HistoryStore {
+    /// `None` until [`start`] ran, samples are only kept in memory until then.
+    dir: Option<PathBuf>,
+    endpoints: HashMap<String, Vec<HistorySample>>,
+    dirty: bool,
+}
+
+impl HistoryStore {
+    fn record(&mut self, key: String, sample: HistorySample) {
+        if !self.endpoints.contains_key(&key)
+            && self.endpoints.len() >= SERVER_HISTORY_MAX_ENDPOINTS
+        {
+            // make room by dropping the endpoint that was queried the longest ago
+            let oldest = self
+                .endpoints
+                .iter()
+                .min_by_key(|(_, samples)| samples.last().map_or(0, |s| s.time))
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.endpoints.remove(&oldest);
+            }
+        }
+
+        let samples = self.endpoints.entry(key).or_default();
+        match samples.last_mut() {
+            Some(last) if last.time == sample.time => last.merge(&sample),
+            _ => samples.push(sample),
+        }
+        self.dirty = true;
+    }
+
+    /// Downsamples samples older than `SERVER_HISTORY_RECENT_SECS` and drops
+    /// the ones older than `SERVER_HISTORY_RETENTION_SECS`.
+    fn compact(&mut self, now: u64) {
+        let expired_before = now.saturating_sub(SERVER_HISTORY_RETENTION_SECS);
+        let old_before = now.saturating_sub(SERVER_HISTORY_RECENT_SECS);
+
+        for samples in self.endpoints.values_mut() {
+            let len = samples.len();
+            samples.retain(|s| s.time >= expired_before);
+
+            let recent_start = samples.partition_point(|s| s.time < old_before);
+            let recent = samples.split_off(recent_start);
+            let old = downsample(std::mem::take(samples), SERVER_HISTORY_OLD_BUCKET_SECS);
+            *samples = old;
+            samples.extend(recent);
+
+            if samples.len() != len {
+                self.dirty = true;
+            }
+        }
+
+        let len = self.endpoints.len();
+        self.endpoints.retain(|_, samples| !samples.is_empty());
+        if self.endpoints.len() != len {
+            self.dirty = true;
+        }
+    }
+}
+
+fn lock_history() -> std::sync::MutexGuard<'static, HistoryStore> {
+    match HISTORY.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn endpoint_key(ip: &str, port: i32) -> String {
+    format!("{}:{}", ip.trim().to_lowercase(), port)
+}
+
+/// Records the outcome of a query of `ip:port`. Only queries that asked for
+/// the info section are recorded, since that is what tells whether the
+/// server is online. Callers record single server queries and watched
+/// servers, not whole list refreshes, so the servers the user cares about
+/// are not evicted by the `SERVER_HISTORY_MAX_ENDPOINTS` cap.
+pub fn record(ip: &str, port: i32, response: &ServerQueryResponse) {
+    let Some(info) = &response.info else {
+        return;
+    };
+    if !response.stale.is_empty() {
+        return;
+    }
+
+    let players = match info {
+        QueryOutcome::Ok(info) => Some(info.players),
+        QueryOutcome::Error(_) => None,
+    };
+    let ping = response
+        .ping_stats
+        .as_ref()
+        .filter(|stats| stats.received > 0)
+        .map(|stats| stats.avg_ms);
+
+    let now = now_secs();
+    let sample = HistorySample::new(
+        now - now % SERVER_HISTORY_RECENT_BUCKET_SECS,
+        players.is_some(),
+        ping,
+        players,
+    );
+    lock_history().record(endpoint_key(ip, port), sample);
+}
+
+fn history_path(dir: &Path) -> PathBuf {
+    dir.join(SERVER_HISTORY_FILE)
+}
+
+fn read_history(dir: &Path) -> HashMap<String, Vec<HistorySample>> {
+    let Ok(data) = fs::read(history_path(dir)) else {
+        return HashMap::new();
+    };
+    match serde_json::from_slice::<HistoryFile>(&data) {
+        Ok(file) => file.endpoints.into_iter().collect(),
+        Err(e) => {
+            log::warn!("Ignoring unreadable server history: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+fn write_history(dir: &Path, file: &HistoryFile) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = history_path(dir);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_vec(file)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Compacts the history and writes it to disk if anything changed. Called
+/// periodically and when the launcher exits.
+pub fn flush() {
+    let (dir, file) = {
+        let mut store = lock_history();
+        store.compact(now_secs());
+        let Some(dir) = store.dir.clone().filter(|_| store.dirty) else {
+            return;
+        };
+        store.dirty = false;
+
+        let file = HistoryFile {
+            endpoints: store
+                .endpoints
+                .iter()
+                .map(|(key, samples)| (key.clone(), samples.clone()))
+                .collect(),
+        };
+        (dir, file)
+    };
+
+    if let Err(e) = write_history(&dir, &file) {
+        log::warn!("Failed to store server history: {}", e);
+        lock_history().dirty = true;
+    }
+}
+
+/// Loads the stored history and starts the task that periodically writes it
+/// back to the local data dir.
+pub fn start() {
+    let dir = match helpers::local_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("{}, server history will not be stored", e);
+            return;
+        }
+    };
+
+    {
+        let loaded = read_history(&dir);
+        let mut store = lock_history();
+        for (key, mut samples) in loaded {
+            // samples recorded before the file was loaded are newer
+            if let Some(recorded) = store.endpoints.remove(&key) {
+                samples.extend(recorded);
+            }
+            store.endpoints.insert(key, samples);
+        }
+        store.dir = Some(dir);
+    }
+
+    tauri::async_runtime::spawn(async {
+        let mut ticker =
+            tokio::time::interval(Duration::from_secs(SERVER_HISTORY_FLUSH_INTERVAL_SECS));
+        loop {
+            ticker.tick().await;
+            flush();
+        }
+    });
+}
+
+/// Returns the samples of `ip:port` between the unix times `from` and `to`
+/// (both in seconds, inclusive), oldest first. If `resolution_secs` is given,
+/// samples are merged into buckets of that size.
+#[tauri::command]
+pub fn get_server_history(
+    ip: String,
+    port: i32,
+    from: Option<u64>,
+    to: Option<u64>,
+    resolution_secs: Option<u64>,
+) -> Result<Vec<HistorySample>> {
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(u64::MAX);
+    if from > to {
+        return Err(LauncherError::InvalidInput(format!(
+            "Invalid history range {}-{}",
+            from, to
+        )));
+    }
+
+    let samples: Vec<HistorySample> = lock_history()
+        .endpoints
+        .get(&endpoint_key(&ip, port))
+        .map(|samples| {
+            samples
+                .iter()
+                .filter(|s| s.time >= from && s.time <= to)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(match resolution_secs.filter(|&secs| secs > 0) {
+        Some(secs) => downsample(samples, secs),
+        None => samples,
+    })
+}
+
+/// Forgets the history of `ip:port`, or of every server if no endpoint is given.
+#[tauri::command]
+pub fn clear_server_history(ip: Option<String>, port: Option<i32>) -> Result<()> {
+    let mut store = lock_history();
+    match (ip, port) {
+        (Some(ip), Some(port)) => {
+            store.endpoints.remove(&endpoint_key(&ip, port));
+        }
+        (None, None) => store.endpoints.clear(),
+        _ => {
+            return Err(LauncherError::InvalidInput(
+                "Both ip and port are needed to clear the history of a server".to_string(),
+            ));
+        }
+    }
+    store.dirty = true;
+    drop(store);
+
+    flush();
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::batch_query::{BatchEndpoint, BatchQueryEngine, BatchQueryParams};
+use crate::query::{InfoPacket, QueryOutcome};
+use crate::server_history;
+use crate::{constants::*, errors::*};
+
+static WATCH_STATE: Lazy<Mutex<WatchState>> = Lazy::new(|| Mutex::new(WatchState::default()));
+
+#[derive(Default)]
+struct WatchState {
+    /// Endpoint sets by subscription name, e.g. "favorites" or "selected".
+    subscriptions: HashMap<String, Subscription>,
+    endpoints: HashMap<BatchEndpoint, WatchedEndpoint>,
+}
+
+struct Subscription {
+    servers: Vec<BatchEndpoint>,
+    interval: Duration,
+}
+
+struct WatchedEndpoint {
+    next_due: Instant,
+    /// Set while a query is running, a slow server is not queried again
+    /// before it answered or timed out.
+    querying: bool,
+    last: Option<ServerSnapshot>,
+}
+
+/// The parts of a server's state that are worth telling the frontend about.
+#[derive(Clone, PartialEq)]
+struct ServerSnapshot {
+    online: bool,
+    hostname: String,
+    players: u16,
+    max_players: u16,
+    password: bool,
+}
+
+impl ServerSnapshot {
+    fn from_info(info: Option<&InfoPacket>) -> Self {
+        match info {
+            Some(info) => Self {
+                online: true,
+                hostname: info.hostname.clone(),
+                players: info.players,
+                max_players: info.max_players,
+                password: info.password,
+            },
+            None => Self {
+                online: false,
+                hostname: String::new(),
+                players: 0,
+                max_players: 0,
+                password: false,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerUpdate {
+    pub ip: String,
+    pub port: i32,
+    pub online: bool,
+    /// `None` while the server is offline.
+    pub info: Option<InfoPacket>,
+}
+
+fn lock_state() -> std::sync::MutexGuard<'static, WatchState> {
+    match WATCH_STATE.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl WatchState {
+    /// Drops endpoints no subscription refers to anymore and adds new ones,
+    /// which are due right away.
+    fn sync_endpoints(&mut self) {
+        let subscriptions = &self.subscriptions;
+        self.endpoints.retain(|endpoint, _| {
+            subscriptions
+                .values()
+                .any(|sub| sub.servers.contains(endpoint))
+        });
+
+        let now = Instant::now();
+        for sub in self.subscriptions.values() {
+            for endpoint in &sub.servers {
+                self.endpoints
+                    .entry(endpoint.clone())
+                    .or_insert(WatchedEndpoint {
+                        next_due: now,
+                        querying: false,
+                        last: None,
+                    });
+            }
+        }
+    }
+
+    /// Shortest interval of all subscriptions watching `endpoint`.
+    fn interval_of(&self, endpoint: &BatchEndpoint) -> Duration {
+        self.subscriptions
+            .values()
+            .filter(|sub| sub.servers.contains(endpoint))
+            .map(|sub| sub.interval)
+            .min()
+            .unwrap_or(Duration::from_millis(SERVER_WATCH_DEFAULT_INTERVAL_MS))
+    }
+
+    /// Returns the endpoints due for a query, marks them as being queried and
+    /// schedules their next one.
+    fn take_due(&mut self) -> Vec<BatchEndpoint> {
+        let now = Instant::now();
+        let due: Vec<BatchEndpoint> = self
+            .endpoints
+            .iter()
+            .filter(|(_, watched)| !watched.querying && watched.next_due <= now)
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect();
+
+        for endpoint in &due {
+            let interval = self.interval_of(endpoint);
+            if let Some(watched) = self.endpoints.get_mut(endpoint) {
+                watched.next_due = now + interval;
+                watched.querying = true;
+            }
+        }
+        due
+    }
+
+    /// Stores the new state of `endpoint` once its query is done, returns
+    /// whether it changed.
+    fn record(&mut self, endpoint: &BatchEndpoint, snapshot: ServerSnapshot) -> bool {
+        let Some(watched) = self.endpoints.get_mut(endpoint) else {
+            // unsubscribed while the query was running
+            return false;
+        };
+
+        watched.querying = false;
+        if watched.last.as_ref() == Some(&snapshot) {
+            return false;
+        }
+        watched.last = Some(snapshot);
+        true
+    }
+}
+
+/// Starts the background task that queries watched servers and emits a
+/// `server-updated` event whenever one of them changes.
+pub fn start(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(run(app_handle));
+}
+
+async fn run(app_handle: tauri::AppHandle) {
+    let params = BatchQueryParams {
+        info: true,
+        extra_info: false,
+        players: false,
+        rules: false,
+        // a single probe, enough for the ping history of watched servers
+        ping: true,
+        ping_probes: Some(SERVER_WATCH_PING_PROBES),
+        budget_ms: None,
+    };
+
+    let mut ticker = tokio::time::interval(Duration::from_millis(SERVER_WATCH_TICK_MS));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+
+        let due = lock_state().take_due();
+        if due.is_empty() {
+            continue;
+        }
+
+        let engine = match BatchQueryEngine::shared().await {
+            Ok(engine) => engine,
+            Err(e) => {
+                log::warn!("Server watch cannot query servers: {}", e);
+                continue;
+            }
+        };
+
+        // each query reports on its own, an offline server running out its
+        // budget must not hold back the others
+        for endpoint in due {
+            tauri::async_runtime::spawn(query_watched(
+                app_handle.clone(),
+                engine,
+                endpoint,
+                params,
+            ));
+        }
+    }
+}
+
+async fn query_watched(
+    app_handle: tauri::AppHandle,
+    engine: &'static BatchQueryEngine,
+    endpoint: BatchEndpoint,
+    params: BatchQueryParams,
+) {
+    let info = match engine
+        .query_endpoint(&endpoint.ip, endpoint.port, params)
+        .await
+    {
+        Ok(response) => {
+            server_history::record(&endpoint.ip, endpoint.port, &response);
+            match response.info {
+                Some(QueryOutcome::Ok(info)) => Some(info),
+                _ => None,
+            }
+        }
+        Err(_) => None,
+    };
+
+    let snapshot = ServerSnapshot::from_info(info.as_ref());
+    if !lock_state().record(&endpoint, snapshot) {
+        return;
+    }
+
+    let _ = app_handle.emit_all(
+        "server-updated",
+        ServerUpdate {
+            ip: endpoint.ip,
+            port: endpoint.port,
+            online: info.is_some(),
+            info,
+        },
+    );
+}
+
+/// Replaces the set of servers watched under `name`. The interval is clamped
+/// to `SERVER_WATCH_MIN_INTERVAL_MS`, the shortest interval wins for servers
+/// in more than one set.
+#[tauri::command]
+pub fn watch_servers(
+    name: String,
+    servers: Vec<BatchEndpoint>,
+    interval_ms: Option<u64>,
+) -> Result<()> {
+    if name.is_empty() {
+        return Err(LauncherError::InvalidInput(
+            "Watch name cannot be empty".to_string(),
+        ));
+    }
+
+    let interval = Duration::from_millis(
+        interval_ms
+            .unwrap_or(SERVER_WATCH_DEFAULT_INTERVAL_MS)
+            .max(SERVER_WATCH_MIN_INTERVAL_MS),
+    );
+
+    let mut state = lock_state();
+    state
+        .subscriptions
+        .insert(name, Subscription { servers, interval });
+    state.sync_endpoints();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_servers(name: String) -> Result<()> {
+    let mut state = lock_state();
+    state.subscriptions.remove(&name);
+    state.sync_endpoints();
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{constants::*, errors::*, helpers, link_policy::LinkPolicy};
+
+/// The launch related settings of the frontend, mirrored into
+/// `LAUNCHER_SETTINGS_FILE` so they are known before the window opens.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LauncherSettings {
+    pub nick_name: String,
+    pub gtasa_path: String,
+    /// One of the frontend's `SAMP_DLL_VERSIONS`, e.g. `037R5_samp.dll`.
+    pub samp_version: String,
+    pub custom_game_exe: String,
+    /// Launch the game right away when an omp:// or samp:// link is opened,
+    /// instead of asking in the launcher window. Links the `link_policy`
+    /// wants confirmed are still asked about.
+    pub direct_links: bool,
+    /// Link schemes the launcher registers itself for on startup, changed
+    /// with `set_scheme_claimed` rather than by the frontend's settings.
+    pub claimed_schemes: Vec<String>,
+    /// Changed with `set_link_policy` rather than by the frontend's settings.
+    pub link_policy: LinkPolicy,
+    /// Unix time in milliseconds the last link was let through, kept here
+    /// so the link cooldown holds across launcher processes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_link_at: Option<u64>,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        Self {
+            nick_name: String::new(),
+            gtasa_path: String::new(),
+            samp_version: String::new(),
+            custom_game_exe: String::new(),
+            direct_links: false,
+            claimed_schemes: vec![
+                DEEPLINK_SCHEME_OMP.to_string(),
+                DEEPLINK_SCHEME_SAMP.to_string(),
+            ],
+            link_policy: LinkPolicy::default(),
+            last_link_at: None,
+        }
+    }
+}
+
+fn settings_path() -> Result<PathBuf> {
+    Ok(helpers::local_data_dir()?.join(LAUNCHER_SETTINGS_FILE))
+}
+
+/// The stored settings, defaults if there are none or they are unreadable.
+pub fn load() -> LauncherSettings {
+    let Ok(data) = settings_path().and_then(|path| Ok(fs::read(path)?)) else {
+        return LauncherSettings::default();
+    };
+    serde_json::from_slice(&data).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable launcher settings: {}", e);
+        LauncherSettings::default()
+    })
+}
+
+pub fn save(settings: &LauncherSettings) -> Result<()> {
+    let path = settings_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_launcher_settings() -> LauncherSettings {
+    load()
+}
+
+#[tauri::command]
+pub fn set_launcher_settings(mut settings: LauncherSettings) -> Result<()> {
+    let stored = load();
+    settings.claimed_schemes = stored.claimed_schemes;
+    settings.link_policy = stored.link_policy;
+    settings.last_link_at = stored.last_link_at;
+    save(&settings)
+}
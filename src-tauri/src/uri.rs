@@ -0,0 +1,241 @@
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+
+use crate::{constants::*, errors::*, validation};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum UriScheme {
+    Omp,
+    Samp,
+}
+
+/// A SA-MP client version the launcher ships, named like its directory in the
+/// samp resource dir.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClientVersion {
+    #[serde(rename = "0.3.7-R1")]
+    R1,
+    #[serde(rename = "0.3.7-R2")]
+    R2,
+    #[serde(rename = "0.3.7-R3")]
+    R3,
+    #[serde(rename = "0.3.7-R3-1")]
+    R3_1,
+    #[serde(rename = "0.3.7-R4")]
+    R4,
+    #[serde(rename = "0.3.7-R5")]
+    R5,
+    #[serde(rename = "0.3.DL")]
+    DL,
+    /// The samp.dll in the game directory.
+    #[serde(rename = "custom")]
+    Custom,
+}
+
+impl ClientVersion {
+    const ALL: [ClientVersion; 8] = [
+        ClientVersion::R1,
+        ClientVersion::R2,
+        ClientVersion::R3,
+        ClientVersion::R3_1,
+        ClientVersion::R4,
+        ClientVersion::R5,
+        ClientVersion::DL,
+        ClientVersion::Custom,
+    ];
+
+    /// Directory of its samp.dll in the samp resource dir, `None` for `Custom`.
+    pub fn dir(self) -> Option<&'static str> {
+        match self {
+            ClientVersion::R1 => Some("0.3.7-R1"),
+            ClientVersion::R2 => Some("0.3.7-R2"),
+            ClientVersion::R3 => Some("0.3.7-R3"),
+            ClientVersion::R3_1 => Some("0.3.7-R3-1"),
+            ClientVersion::R4 => Some("0.3.7-R4"),
+            ClientVersion::R5 => Some("0.3.7-R5"),
+            ClientVersion::DL => Some("0.3.DL"),
+            ClientVersion::Custom => None,
+        }
+    }
+
+    /// The key the frontend stores the version under, e.g. `037R5_samp.dll`.
+    fn setting_key(self) -> &'static str {
+        match self {
+            ClientVersion::R1 => "037R1_samp.dll",
+            ClientVersion::R2 => "037R2_samp.dll",
+            ClientVersion::R3 => "037R3_samp.dll",
+            ClientVersion::R3_1 => "037R31_samp.dll",
+            ClientVersion::R4 => "037R4_samp.dll",
+            ClientVersion::R5 => "037R5_samp.dll",
+            ClientVersion::DL => "03DL_samp.dll",
+            ClientVersion::Custom => "custom",
+        }
+    }
+}
+
+impl FromStr for ClientVersion {
+    type Err = UriError;
+
+    /// Accepts the directory name (`0.3.7-R5`), the frontend's setting key
+    /// (`037R5_samp.dll`) and `custom`, ignoring case.
+    fn from_str(value: &str) -> std::result::Result<Self, UriError> {
+        ClientVersion::ALL
+            .into_iter()
+            .find(|version| {
+                version.setting_key().eq_ignore_ascii_case(value)
+                    || version
+                        .dir()
+                        .is_some_and(|dir| dir.eq_ignore_ascii_case(value))
+            })
+            .ok_or_else(|| UriError::UnknownVersion(value.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriError {
+    UnsupportedScheme(String),
+    Malformed(String),
+    MissingHost,
+    InvalidHost(String),
+    InvalidPort(String),
+    InvalidNickname(String),
+    UnknownVersion(String),
+}
+
+impl fmt::Display for UriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UriError::UnsupportedScheme(scheme) => {
+                write!(
+                    f,
+                    "Unsupported scheme '{}', expected omp:// or samp://",
+                    scheme
+                )
+            }
+            UriError::Malformed(msg) => write!(f, "Malformed link: {}", msg),
+            UriError::MissingHost => write!(f, "The link has no server address"),
+            UriError::InvalidHost(msg) => write!(f, "Invalid server address: {}", msg),
+            UriError::InvalidPort(port) => write!(f, "Invalid server port '{}'", port),
+            UriError::InvalidNickname(msg) => write!(f, "Invalid nickname: {}", msg),
+            UriError::UnknownVersion(version) => {
+                write!(f, "Unknown client version '{}'", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UriError {}
+
+impl From<UriError> for LauncherError {
+    fn from(err: UriError) -> Self {
+        LauncherError::InvalidInput(err.to_string())
+    }
+}
+
+/// A parsed `omp://host[:port][?password=..&nickname=..&version=..]` link.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ServerUri {
+    pub scheme: UriScheme,
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+    pub nickname: Option<String>,
+    pub version: Option<ClientVersion>,
+}
+
+/// Parses an `omp://` or `samp://` link. The port defaults to
+/// `DEFAULT_SERVER_PORT`; `name` and `client` are accepted as aliases of
+/// `nickname` and `version`. Unknown parameters are ignored, so links made
+/// for newer launchers still open.
+pub fn parse(input: &str) -> std::result::Result<ServerUri, UriError> {
+    let input = input.trim();
+    let url = Url::parse(input).map_err(|e| match e {
+        url::ParseError::EmptyHost => UriError::MissingHost,
+        url::ParseError::InvalidPort => {
+            let port = input
+                .split("://")
+                .nth(1)
+                .and_then(|rest| rest.split(['/', '?']).next())
+                .and_then(|authority| authority.rsplit_once(':'))
+                .map(|(_, port)| port.to_string())
+                .unwrap_or_default();
+            UriError::InvalidPort(port)
+        }
+        url::ParseError::RelativeUrlWithoutBase => {
+            UriError::Malformed("expected an omp:// or samp:// link".to_string())
+        }
+        other => UriError::Malformed(other.to_string()),
+    })?;
+
+    let scheme = match url.scheme() {
+        s if s == DEEPLINK_SCHEME_OMP => UriScheme::Omp,
+        s if s == DEEPLINK_SCHEME_SAMP => UriScheme::Samp,
+        other => return Err(UriError::UnsupportedScheme(other.to_string())),
+    };
+
+    let host = url
+        .host_str()
+        .filter(|host| !host.is_empty())
+        .ok_or(UriError::MissingHost)?;
+    let host =
+        validation::validate_hostname(host).map_err(|e| UriError::InvalidHost(e.to_string()))?;
+
+    let port = match url.port() {
+        Some(0) => return Err(UriError::InvalidPort("0".to_string())),
+        Some(port) => port,
+        None => DEFAULT_SERVER_PORT,
+    };
+
+    if !matches!(url.path(), "" | "/") {
+        return Err(UriError::Malformed(format!(
+            "unexpected path '{}'",
+            url.path()
+        )));
+    }
+
+    let mut uri = ServerUri {
+        scheme,
+        host,
+        port,
+        password: None,
+        nickname: None,
+        version: None,
+    };
+
+    // decoded by hand, query_pairs() would turn a '+' in a password into a space
+    let pairs = url.query().unwrap_or_default().split('&');
+    for pair in pairs.filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode_str(value).decode_utf8_lossy();
+        match percent_decode_str(name).decode_utf8_lossy().as_ref() {
+            "password" | "pass" => {
+                let password = validation::sanitize_password(&value);
+                uri.password = Some(password).filter(|p| !p.is_empty());
+            }
+            "nickname" | "name" => {
+                let nickname = validation::validate_player_name(&value)
+                    .map_err(|e| UriError::InvalidNickname(e.to_string()))?;
+                uri.nickname = Some(nickname);
+            }
+            "version" | "client" => uri.version = Some(value.parse()?),
+            other => log::info!("Ignoring unknown link parameter '{}'", other),
+        }
+    }
+
+    Ok(uri)
+}
+
+/// Whether `arg` looks like a link the launcher handles.
+pub fn is_server_uri(arg: &str) -> bool {
+    let arg = arg.trim_start().to_ascii_lowercase();
+    arg.starts_with("omp://") || arg.starts_with("samp://")
+}
+
+#[tauri::command]
+pub fn parse_server_uri(uri: String) -> Result<ServerUri> {
+    Ok(parse(&uri)?)
+}
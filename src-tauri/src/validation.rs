@@ -1,4 +1,5 @@
 use crate::errors::*;
+use std::net::Ipv6Addr;
 use std::path::Path;
 
 pub fn validate_port(port: i32) -> Result<u16> {
@@ -20,6 +21,20 @@ pub fn validate_hostname(hostname: &str) -> Result<String> {
 
     let trimmed = hostname.trim();
 
+    // IPv6 literals have to be bracketed, like in URLs
+    if let Some(inner) = trimmed
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return match inner.parse::<Ipv6Addr>() {
+            Ok(_) => Ok(trimmed.to_string()),
+            Err(_) => Err(LauncherError::InvalidInput(format!(
+                "Invalid IPv6 address: '{}'",
+                inner
+            ))),
+        };
+    }
+
     // Basic length check
     if trimmed.len() > 253 {
         return Err(LauncherError::InvalidInput("Hostname too long".to_string()));
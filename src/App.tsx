@@ -20,7 +20,7 @@ import LoadingScreen from "./containers/LoadingScreen";
 import WindowTitleBar from "./containers/WindowTitleBar";
 import { changeLanguage } from "./locales";
 import { useGenericPersistentState } from "./states/genericStates";
-import { usePersistentServers } from "./states/servers";
+import { syncFavorites, usePersistentServers } from "./states/servers";
 import { useTheme } from "./states/theme";
 import { throttle } from "./utils/debounce";
 import {
@@ -29,8 +29,9 @@ import {
   fetchUpdateInfo,
   generateLanguageFilters,
 } from "./utils/helpers";
+import { importPendingFavorites } from "./utils/game";
 import PerformanceMonitor from "./utils/performance";
-import { PING_TIMEOUT_VALUE } from "./utils/query";
+import { listenToServerUpdates, PING_TIMEOUT_VALUE } from "./utils/query";
 import { sc } from "./utils/sizeScaler";
 
 const LOADING_WINDOW_SIZE = new LogicalSize(250, 300);
@@ -155,6 +156,7 @@ const App = memo(() => {
 
   useEffect(() => {
     let killResizeListener: (() => void) | null = null;
+    let killServerUpdatesListener: (() => void) | null = null;
 
     const setupListeners = async () => {
       // Optimize context menu handler
@@ -168,6 +170,12 @@ const App = memo(() => {
       }
 
       killResizeListener = await appWindow.onResized(windowResizeListener);
+
+      if (!IN_GAME) {
+        killServerUpdatesListener = await listenToServerUpdates();
+        syncFavorites(usePersistentServers.getState().favorites);
+        importPendingFavorites();
+      }
     };
 
     const setupGameMonitoring = () => {
@@ -178,7 +186,7 @@ const App = memo(() => {
             if (!isAlive) {
               await invoke("send_message_to_game", {
                 id: IN_GAME_PROCESS_ID,
-                message: "close_overlay",
+                message: { type: "close_overlay" },
               });
               setTimeout(() => appWindow.close(), 300);
             }
@@ -206,6 +214,7 @@ const App = memo(() => {
 
     return () => {
       killResizeListener?.();
+      killServerUpdatesListener?.();
       if (processCheckInterval.current) {
         clearInterval(processCheckInterval.current);
       }
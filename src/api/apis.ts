@@ -1,8 +1,9 @@
+import { invoke } from "@tauri-apps/api";
 import api from "../api/config";
 import { UpdateInfo } from "../states/app";
-import { mapAPIResponseServerListToAppStructure } from "../utils/helpers";
+import { mapMasterListToAppStructure } from "../utils/helpers";
 import { Log } from "../utils/logger";
-import { APIResponseServer, Server } from "../utils/types";
+import { MasterList, Server } from "../utils/types";
 
 interface ApiResponse<T> {
   success: boolean;
@@ -12,16 +13,17 @@ interface ApiResponse<T> {
 
 export const getCachedList = async (): Promise<ApiResponse<Server[]>> => {
   try {
-    const response = await api.get<APIResponseServer[]>("/servers/full");
-
-    if (!Array.isArray(response.data)) {
-      Log.debug("Invalid API response: expected array");
-      return { success: false, data: [], error: "Invalid response format" };
+    // fetched natively, which falls back to the last stored snapshot when
+    // the master list cannot be reached
+    const response = await invoke<MasterList>("get_master_list");
+    if (response.stale) {
+      Log.warn(
+        "Master list unreachable, showing servers from",
+        new Date(response.fetched_at * 1000).toISOString()
+      );
     }
 
-    const restructuredList = mapAPIResponseServerListToAppStructure(
-      response.data
-    );
+    const restructuredList = mapMasterListToAppStructure(response.servers);
 
     // Update server store with the fetched data
     const { useServers } = await import("../states/servers");
@@ -33,7 +35,7 @@ export const getCachedList = async (): Promise<ApiResponse<Server[]>> => {
     return {
       success: false,
       data: [],
-      error: error instanceof Error ? error.message : "Unknown error",
+      error: error instanceof Error ? error.message : String(error),
     };
   }
 };
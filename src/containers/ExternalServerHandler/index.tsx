@@ -12,159 +12,144 @@ import Icon from "../../components/Icon";
 import StaticModal from "../../components/StaticModal";
 import Text from "../../components/Text";
 import { images } from "../../constants/images";
+import { useMessageBox } from "../../states/messageModal";
 import { usePersistentServers } from "../../states/servers";
 import { useSettings } from "../../states/settings";
 import { useTheme } from "../../states/theme";
 import { startGame } from "../../utils/game";
+import { getSampVersionFromName } from "../../utils/helpers";
 import { Log } from "../../utils/logger";
 import { sc } from "../../utils/sizeScaler";
 import { Server } from "../../utils/types";
-import {
-  isValidDomain,
-  validateServerAddressIPv4,
-} from "../../utils/validation";
+
+interface ServerLink {
+  host: string;
+  port: number;
+  password: string | null;
+  nickname: string | null;
+  /** Client version name, e.g. "0.3.7-R5", or "custom". */
+  version: string | null;
+}
+
+type LinkFlag =
+  | { kind: "unlisted_host" }
+  | { kind: "password" }
+  | { kind: "nickname"; nickname: string }
+  | { kind: "version"; version: string };
+
+interface LinkCheck {
+  link: string;
+  uri: ServerLink | null;
+  verdict: "allow" | "confirm" | "block";
+  flags: LinkFlag[];
+  reason: string | null;
+}
+
+const flagText = (flag: LinkFlag) => {
+  switch (flag.kind) {
+    case "nickname":
+      return t("external_link_nickname", { nickname: flag.nickname });
+    case "version":
+      return t("external_link_version", { version: flag.version });
+    default:
+      return t(`external_link_${flag.kind}`);
+  }
+};
+
+const emptyServer = (link: ServerLink): Server => ({
+  ip: link.host,
+  port: link.port,
+  hostname: `No information (${link.host}:${link.port})`,
+  playerCount: 0,
+  maxPlayers: 0,
+  gameMode: "-",
+  language: "-",
+  hasPassword: false,
+  version: "-",
+  usingOmp: false,
+  partner: false,
+  ping: 0,
+  players: [],
+  password: link.password ?? "",
+  rules: {} as Server["rules"],
+});
 
 const ExternalServerHandler = () => {
   const [visible, showModal] = useState(false);
   const { nickName, gtasaPath } = useSettings();
   const { height, width } = useWindowDimensions();
   const { theme } = useTheme();
-  const [serverAddress, setServerAddress] = useState("");
+  const [link, setLink] = useState<ServerLink>();
+  const [flags, setFlags] = useState<LinkFlag[]>([]);
   const { addToFavorites } = usePersistentServers();
 
   useEffect(() => {
-    try {
-      (async () => {
-        const value = await invoke<string>("get_uri_scheme_value");
-        if (
-          value.length &&
-          (value.includes("omp://") || value.includes("samp://"))
-        ) {
-          const serverAddress = value
-            .replace("omp://", "")
-            .replace("samp://", "")
-            .replace("/", "");
+    const openLink = (check: LinkCheck) => {
+      if (check.verdict === "block" || !check.uri) {
+        Log.warn("Ignoring server link:", check.link, check.reason);
+        return;
+      }
 
-          showModal(true);
-          setServerAddress(serverAddress);
+      setLink(check.uri);
+      setFlags(check.flags);
+      showModal(true);
+    };
+
+    invoke<string>("get_uri_scheme_value")
+      .then(async (value) => {
+        if (value.includes("omp://") || value.includes("samp://")) {
+          openLink(
+            await invoke<LinkCheck>("check_server_link", { uri: value })
+          );
         }
-      })();
-    } catch (e) {
-      Log.error(e);
-    }
+      })
+      .catch((e) => Log.error(e));
 
-    const unlisten = listen<string>("scheme-request-received", (event) => {
-      if (typeof event.payload === "string") {
-        if (
-          event.payload.includes("omp://") ||
-          event.payload.includes("samp://")
-        ) {
-          const serverAddress = event.payload
-            .replace("omp://", "")
-            .replace("samp://", "")
-            .replace("/", "");
+    const unlisten = listen<LinkCheck>("scheme-request-received", (event) =>
+      openLink(event.payload)
+    );
 
-          showModal(true);
-          setServerAddress(serverAddress);
-        }
-      }
+    // launches handed over by another instance report back only on failure
+    const unlistenLaunch = listen<string>("handoff-launch-failed", (event) => {
+      const { showMessageBox, hideMessageBox } = useMessageBox.getState();
+      showMessageBox({
+        title: t("external_launch_failed_title"),
+        description: event.payload,
+        buttons: [{ title: "OK", onPress: hideMessageBox }],
+      });
     });
 
     return () => {
       unlisten.then((f) => f());
+      unlistenLaunch.then((f) => f());
     };
   }, []);
 
-  if (!visible) {
-    return null;
-  }
-
   const addServer = useCallback(() => {
-    const serverInfo: Server = {
-      ip: "",
-      port: 0,
-      hostname: "No information",
-      playerCount: 0,
-      maxPlayers: 0,
-      gameMode: "-",
-      language: "-",
-      hasPassword: false,
-      version: "-",
-      usingOmp: false,
-      partner: false,
-      ping: 0,
-      players: [],
-      password: "",
-      rules: {} as Server["rules"],
-    };
-
-    if (serverAddress.length) {
-      if (serverAddress.includes(":")) {
-        const data = serverAddress.split(":");
-        serverInfo.ip = data[0];
-        serverInfo.port = parseInt(data[1]);
-        serverInfo.hostname += ` (${serverInfo.ip}:${serverInfo.port})`;
-      } else {
-        if (
-          validateServerAddressIPv4(serverAddress) ||
-          isValidDomain(serverAddress)
-        ) {
-          serverInfo.ip = serverAddress;
-          serverInfo.port = 7777;
-          serverInfo.hostname += ` (${serverInfo.ip}:${serverInfo.port})`;
-        }
-      }
-
-      addToFavorites(serverInfo);
+    if (link) {
+      addToFavorites(emptyServer(link));
       showModal(false);
     }
-  }, [serverAddress, addToFavorites]);
+  }, [link, addToFavorites]);
 
   const joinServer = useCallback(() => {
-    const serverInfo: Server = {
-      ip: "",
-      port: 0,
-      hostname: "No information",
-      playerCount: 0,
-      maxPlayers: 0,
-      gameMode: "-",
-      language: "-",
-      hasPassword: false,
-      version: "-",
-      usingOmp: false,
-      partner: false,
-      ping: 0,
-      players: [],
-      password: "",
-      rules: {} as Server["rules"],
-    };
-
-    if (serverAddress.length) {
-      if (serverAddress.includes(":")) {
-        const data = serverAddress.split(":");
-        serverInfo.ip = data[0];
-        serverInfo.port = parseInt(data[1]);
-        serverInfo.hostname += ` (${serverInfo.ip}:${serverInfo.port})`;
-      } else {
-        if (
-          validateServerAddressIPv4(serverAddress) ||
-          isValidDomain(serverAddress)
-        ) {
-          serverInfo.ip = serverAddress;
-          serverInfo.port = 7777;
-          serverInfo.hostname += ` (${serverInfo.ip}:${serverInfo.port})`;
-        }
-      }
-
-      startGame(serverInfo, nickName, gtasaPath, "");
+    if (link) {
+      startGame(
+        emptyServer(link),
+        link.nickname ?? nickName,
+        gtasaPath,
+        link.password ?? "",
+        link.version ? getSampVersionFromName(link.version) : undefined
+      );
       showModal(false);
     }
-  }, [serverAddress, nickName, gtasaPath]);
+  }, [link, nickName, gtasaPath]);
 
   const dynamicStyles = useMemo(
     () => ({
       container: {
-        top: height / 2 - 90 - 25,
+        height: 135 + flags.length * 22,
+        top: height / 2 - 90 - 25 - flags.length * 11,
         left: width / 2 - 160,
         backgroundColor: theme.secondary,
       },
@@ -175,9 +160,13 @@ const ExternalServerHandler = () => {
         backgroundColor: theme.primary,
       },
     }),
-    [height, width, theme]
+    [height, width, theme, flags]
   );
 
+  if (!visible || !link) {
+    return null;
+  }
+
   return (
     <StaticModal onDismiss={() => showModal(false)}>
       <View style={[styles.container, dynamicStyles.container]}>
@@ -192,8 +181,19 @@ const ExternalServerHandler = () => {
           color={theme.textPrimary}
           style={[styles.serverAddressText, dynamicStyles.serverAddressText]}
         >
-          {serverAddress}
+          {`${link.host}:${link.port}`}
         </Text>
+        {flags.map((flag) => (
+          <Text
+            key={flag.kind}
+            size={1}
+            color={theme.textSecondary}
+            style={styles.flagText}
+            numberOfLines={1}
+          >
+            {flagText(flag)}
+          </Text>
+        ))}
         <View style={styles.buttonsContainer}>
           <TouchableOpacity
             style={[styles.button, dynamicStyles.button]}
@@ -231,7 +231,6 @@ const ExternalServerHandler = () => {
 const styles = StyleSheet.create({
   container: {
     position: "absolute",
-    height: 135,
     width: 320,
     borderRadius: sc(10),
     shadowColor: "#000",
@@ -257,6 +256,12 @@ const styles = StyleSheet.create({
     width: 300,
     borderRadius: sc(5),
   },
+  flagText: {
+    width: 300,
+    height: 20,
+    lineHeight: 20,
+    marginTop: sc(2),
+  },
   buttonsContainer: {
     width: 300,
     flexDirection: "row",
@@ -46,6 +46,11 @@ const PlayerList = (props: IProps) => {
     return "";
   }, [selected?.omp, themeType]);
 
+  const hasDetails = useMemo(
+    () => props.players.some((player) => player.ping !== undefined),
+    [props.players]
+  );
+
   const renderPlayer = ({
     item: player,
     index,
@@ -57,9 +62,23 @@ const PlayerList = (props: IProps) => {
       >
         <View style={[styles.commonFieldContainer, styles.nameFieldContainer]}>
           <Text style={{ fontSize: sc(16) }} color={theme.textPrimary}>
+            {player.id !== undefined && (
+              <Text style={{ fontSize: sc(14) }} color={theme.textSecondary}>
+                {player.id + "  "}
+              </Text>
+            )}
             {player.name}
           </Text>
         </View>
+        {hasDetails && (
+          <View
+            style={[styles.commonFieldContainer, styles.pingFieldContainer]}
+          >
+            <Text style={{ fontSize: sc(14) }} color={theme.textSecondary}>
+              {player.ping}
+            </Text>
+          </View>
+        )}
         <View
           style={[
             styles.commonFieldContainer,
@@ -102,6 +121,19 @@ const PlayerList = (props: IProps) => {
             {t("player")}
           </Text>
         </View>
+        {hasDetails && (
+          <View
+            style={[styles.commonFieldContainer, styles.pingFieldContainer]}
+          >
+            <Text
+              semibold
+              style={{ fontSize: sc(17) }}
+              color={theme.textSecondary}
+            >
+              {t("ping")}
+            </Text>
+          </View>
+        )}
         <View style={[styles.commonFieldContainer, styles.scoreFieldContainer]}>
           <Text
             semibold
@@ -143,6 +175,11 @@ const styles = StyleSheet.create({
     flex: 1,
     alignItems: "flex-start",
   },
+  pingFieldContainer: {
+    width: sc(60),
+    alignItems: "flex-end",
+    paddingRight: sc(15),
+  },
   scoreFieldContainer: {
     paddingRight: 5,
     alignItems: "flex-end",
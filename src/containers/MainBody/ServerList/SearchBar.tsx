@@ -20,6 +20,7 @@ import { usePersistentServers, useServers } from "../../../states/servers";
 import { useTheme } from "../../../states/theme";
 import { sc } from "../../../utils/sizeScaler";
 import { Log } from "../../../utils/logger";
+import { scanLan } from "../../../utils/query";
 
 interface SearchBarProps {
   onChange: (query: string) => void;
@@ -157,6 +158,10 @@ const SearchBar = memo<SearchBarProps>(({ onChange }) => {
     }
   }, [clearRecentlyJoined]);
 
+  const handleScanLan = useCallback(() => {
+    scanLan();
+  }, []);
+
   return (
     <View style={styles.searchContainer}>
       <TouchableOpacity
@@ -256,6 +261,16 @@ const SearchBar = memo<SearchBarProps>(({ onChange }) => {
             onPress={handleClearRecentlyJoined}
           />
         )}
+        {listType === "lan" && (
+          <ActionIcon
+            title={t("scan_lan")}
+            icon={images.icons.refresh}
+            iconSize={sc(20)}
+            iconColor={theme.textSecondary}
+            buttonColor={theme.itemBackgroundColor}
+            onPress={handleScanLan}
+          />
+        )}
         <ActionIcon
           svg
           title={t("add_server")}
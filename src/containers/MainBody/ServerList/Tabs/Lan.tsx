@@ -0,0 +1,54 @@
+import { useEffect, useMemo } from "react";
+import { useQuery } from "../../../../hooks/query";
+import { useGenericTempState } from "../../../../states/genericStates";
+import { useServers } from "../../../../states/servers";
+import { sortAndSearchInServerList } from "../../../../utils/helpers";
+import { scanLan } from "../../../../utils/query";
+import { Server } from "../../../../utils/types";
+import List from "../List";
+import ServerItem from "./../Item";
+
+const Lan = () => {
+  const { startQuery, stopQuery } = useQuery();
+  const { selected, lanServers, setSelected } = useServers();
+  const { searchData } = useGenericTempState();
+
+  useEffect(() => {
+    scanLan();
+
+    return () => {
+      stopQuery();
+      setSelected(undefined);
+    };
+  }, []);
+
+  const list = useMemo(() => {
+    return sortAndSearchInServerList(lanServers, searchData);
+  }, [lanServers, searchData]);
+
+  const onSelect = (server: Server) => {
+    stopQuery();
+    setSelected(server);
+    startQuery(server, "lan");
+  };
+
+  return (
+    <List
+      data={list}
+      renderItem={(item, index) => (
+        <ServerItem
+          isSelected={
+            selected
+              ? selected.ip === item.ip && selected.port === item.port
+              : false
+          }
+          server={item}
+          index={index}
+          onSelect={(server) => onSelect(server)}
+        />
+      )}
+    />
+  );
+};
+
+export default Lan;
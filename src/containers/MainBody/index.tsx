@@ -10,6 +10,7 @@ import ServerInfo from "./ServerInfo";
 import SearchBar from "./ServerList/SearchBar";
 import Favorites from "./ServerList/Tabs/Favorites";
 import Internet from "./ServerList/Tabs/Internet";
+import Lan from "./ServerList/Tabs/Lan";
 import Partners from "./ServerList/Tabs/Partners";
 import RecentlyJoined from "./ServerList/Tabs/RecentlyJoined";
 
@@ -35,6 +36,8 @@ const MainView = memo(() => {
         return <Internet />;
       case "recentlyjoined":
         return <RecentlyJoined />;
+      case "lan":
+        return <Lan />;
       default:
         return <Internet />;
     }
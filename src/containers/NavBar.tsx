@@ -60,6 +60,11 @@ const NavBar = memo(() => {
         label: t("recently_joined"),
         type: "recentlyjoined" as ListType,
       },
+      {
+        icon: images.icons.internet,
+        label: t("lan"),
+        type: "lan" as ListType,
+      },
     ],
     [t, i18n.language]
   );
@@ -1,5 +1,14 @@
+import { invoke } from "@tauri-apps/api";
 import { t } from "i18next";
-import { StyleSheet, TextInput, TouchableOpacity, View } from "react-native";
+import { useEffect, useState } from "react";
+import {
+  Pressable,
+  StyleSheet,
+  TextInput,
+  TouchableOpacity,
+  View,
+} from "react-native";
+import CheckBox from "../../../components/CheckBox";
 import Text from "../../../components/Text";
 import { IN_GAME } from "../../../constants/app";
 import { useSettings } from "../../../states/settings";
@@ -8,11 +17,166 @@ import {
   exportFavoriteListFile,
   importFavoriteListFile,
 } from "../../../utils/game";
+import { Log } from "../../../utils/logger";
 import { sc } from "../../../utils/sizeScaler";
 
+interface SchemeStatus {
+  scheme: string;
+  claimed: boolean;
+  handler: string | null;
+  is_ours: boolean;
+}
+
+const LinkHandlers = () => {
+  const { theme } = useTheme();
+  const [schemes, setSchemes] = useState<SchemeStatus[]>([]);
+
+  useEffect(() => {
+    invoke<SchemeStatus[]>("get_scheme_handlers")
+      .then(setSchemes)
+      .catch((e) => Log.warn("Failed to get link handlers:", e));
+  }, []);
+
+  const setClaimed = (scheme: string, claimed: boolean) =>
+    invoke<SchemeStatus[]>("set_scheme_claimed", { scheme, claimed })
+      .then(setSchemes)
+      .catch((e) => Log.warn("Failed to change link handler:", e));
+
+  return (
+    <>
+      {schemes.map((status) => (
+        <Pressable
+          key={status.scheme}
+          style={styles.checkboxContainer}
+          onPress={() => setClaimed(status.scheme, !status.claimed)}
+        >
+          <CheckBox value={status.claimed} style={styles.checkbox} />
+          <View style={{ flex: 1 }}>
+            <Text semibold color={theme.textPrimary} size={2}>
+              {t("settings_advanced_claim_scheme", { scheme: status.scheme })}
+            </Text>
+            <Text color={theme.textSecondary} size={1} numberOfLines={1}>
+              {status.is_ours
+                ? t("settings_advanced_scheme_handler_launcher")
+                : status.handler
+                  ? t("settings_advanced_scheme_handler", {
+                      handler: status.handler,
+                    })
+                  : t("settings_advanced_scheme_no_handler")}
+            </Text>
+          </View>
+        </Pressable>
+      ))}
+    </>
+  );
+};
+
+interface LinkPolicy {
+  mode: "blocklist" | "allowlist";
+  allowedHosts: string[];
+  blockedHosts: string[];
+}
+
+const splitHosts = (text: string) =>
+  text
+    .split(",")
+    .map((host) => host.trim())
+    .filter((host) => host.length);
+
+const LinkPolicySettings = () => {
+  const { theme } = useTheme();
+  const [policy, setPolicy] = useState<LinkPolicy>();
+  const [allowed, setAllowed] = useState("");
+  const [blocked, setBlocked] = useState("");
+
+  const applyPolicy = (policy: LinkPolicy) => {
+    setPolicy(policy);
+    setAllowed(policy.allowedHosts.join(", "));
+    setBlocked(policy.blockedHosts.join(", "));
+  };
+
+  useEffect(() => {
+    invoke<LinkPolicy>("get_link_policy")
+      .then(applyPolicy)
+      .catch((e) => Log.warn("Failed to get link policy:", e));
+  }, []);
+
+  const savePolicy = (changes: Partial<LinkPolicy>) => {
+    if (!policy) {
+      return;
+    }
+    invoke<LinkPolicy>("set_link_policy", { policy: { ...policy, ...changes } })
+      .then(applyPolicy)
+      .catch((e) => Log.warn("Failed to change link policy:", e));
+  };
+
+  if (!policy) {
+    return null;
+  }
+
+  const inputStyle = [
+    styles.pathInput,
+    {
+      color: theme.textPrimary,
+      backgroundColor: theme.textInputBackgroundColor,
+    },
+  ];
+
+  return (
+    <>
+      <Pressable
+        style={styles.checkboxContainer}
+        onPress={() =>
+          savePolicy({
+            mode: policy.mode === "allowlist" ? "blocklist" : "allowlist",
+          })
+        }
+      >
+        <CheckBox value={policy.mode === "allowlist"} style={styles.checkbox} />
+        <Text semibold color={theme.textPrimary} size={2}>
+          {t("settings_advanced_link_allowlist")}
+        </Text>
+      </Pressable>
+      <Text
+        semibold
+        color={theme.textPrimary}
+        size={2}
+        style={styles.hostsLabel}
+      >
+        {t("settings_advanced_link_allowed_hosts")}:
+      </Text>
+      <View style={styles.pathInputContainer}>
+        <TextInput
+          value={allowed}
+          onChangeText={setAllowed}
+          onBlur={() => savePolicy({ allowedHosts: splitHosts(allowed) })}
+          style={inputStyle}
+        />
+      </View>
+      <Text
+        semibold
+        color={theme.textPrimary}
+        size={2}
+        style={styles.hostsLabel}
+      >
+        {t("settings_advanced_link_blocked_hosts")}:
+      </Text>
+      <View style={styles.pathInputContainer}>
+        <TextInput
+          value={blocked}
+          onChangeText={setBlocked}
+          onBlur={() => savePolicy({ blockedHosts: splitHosts(blocked) })}
+          style={inputStyle}
+        />
+      </View>
+    </>
+  );
+};
+
 const Advanced = () => {
   const { theme } = useTheme();
-  const { customGameExe, setCustomGameExe } = useSettings();
+  const { customGameExe, setCustomGameExe, directLinks, setDirectLinks } =
+    useSettings();
   return (
     <View
       style={{
@@ -40,6 +204,17 @@ const Advanced = () => {
               ]}
             />
           </View>
+          <Pressable
+            style={styles.checkboxContainer}
+            onPress={() => setDirectLinks(!directLinks)}
+          >
+            <CheckBox value={directLinks} style={styles.checkbox} />
+            <Text semibold color={theme.textPrimary} size={2}>
+              {t("settings_advanced_direct_links")}
+            </Text>
+          </Pressable>
+          <LinkHandlers />
+          <LinkPolicySettings />
         </View>
       )}
       <View style={{ flex: 1 }} />
@@ -90,6 +265,17 @@ const styles = StyleSheet.create({
     width: "100%",
     marginTop: 7,
   },
+  checkboxContainer: {
+    flexDirection: "row",
+    alignItems: "center",
+    marginTop: 10,
+  },
+  checkbox: {
+    marginRight: sc(8),
+  },
+  hostsLabel: {
+    marginTop: 10,
+  },
   pathInput: {
     paddingHorizontal: sc(10),
     flex: 1,
@@ -199,7 +199,7 @@ const WindowTitleBar = memo(() => {
   const handleReconnect = useCallback(() => {
     invoke("send_message_to_game", {
       id: IN_GAME_PROCESS_ID,
-      message: "reconnect",
+      message: { type: "reconnect" },
     });
   }, []);
 
@@ -219,7 +219,7 @@ const WindowTitleBar = memo(() => {
     if (IN_GAME) {
       invoke("send_message_to_game", {
         id: IN_GAME_PROCESS_ID,
-        message: "close_overlay",
+        message: { type: "close_overlay" },
       });
     }
 
@@ -3,6 +3,8 @@ export default {
   internet: "Internet",
   partners: "Partners",
   recently_joined: "Recently Joined",
+  lan: "LAN",
+  scan_lan: "Scan Local Network",
   nickname: "Nickname",
   settings: "Settings",
   minimize: "Minimize",
@@ -98,8 +100,24 @@ export default {
     "Please choose another version or download and install SA-MP manually.",
   add_or_play_external_server: "Add to favorites or play",
   reconnect: "Reconnect",
+  external_link_unlisted_host: "This server is not in your trusted servers",
+  external_link_password: "This link fills in the server password",
+  external_link_nickname: "This link changes your nickname to {{ nickname }}",
+  external_link_version: "This link uses client version {{ version }}",
+  external_launch_failed_title: "Could not launch the game",
   settings_advanced_discord_status_requires_restart:
     "(Requires restarting the game to take action)",
+  settings_advanced_direct_links:
+    "Launch the game directly when opening omp:// and samp:// links",
+  settings_advanced_claim_scheme: "Open {{ scheme }}:// links with this launcher",
+  settings_advanced_scheme_handler_launcher:
+    "Currently opened with this launcher",
+  settings_advanced_scheme_handler: "Currently opened with {{ handler }}",
+  settings_advanced_scheme_no_handler: "No program opens these links",
+  settings_advanced_link_allowlist:
+    "Ask before opening links to servers that are not trusted",
+  settings_advanced_link_allowed_hosts: "Trusted servers (comma separated)",
+  settings_advanced_link_blocked_hosts: "Blocked servers (comma separated)",
   settings_export_favorite_list_file: "Export favorites list to file",
   settings_import_favorite_list_file: "Import favorites list from file",
   export_no_servers_description:
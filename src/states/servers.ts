@@ -1,9 +1,15 @@
+import { invoke } from "@tauri-apps/api";
 import { emit, listen } from "@tauri-apps/api/event";
 import { appWindow } from "@tauri-apps/api/window";
 import { t } from "i18next";
 import { create } from "zustand";
 import { createJSONStorage, persist } from "zustand/middleware";
-import { queryServer } from "../utils/query";
+import {
+  FAVORITES_WATCH_INTERVAL_MS,
+  queryServer,
+  watchServers,
+} from "../utils/query";
+import { Log } from "../utils/logger";
 import { stateStorage } from "../utils/stateStorage";
 import { PerServerSettings, SAMPDLLVersions, Server } from "../utils/types";
 import { useNotification } from "./notification";
@@ -14,6 +20,8 @@ interface ServersState {
   setSelected: (server?: Server) => void;
   setServers: (list: Server[]) => void;
   updateServer: (server: Server) => void;
+  lanServers: Server[];
+  setLanServers: (list: Server[]) => void;
 }
 
 interface ServersPersistentState {
@@ -69,7 +77,12 @@ const useServers = create<ServersState>()((set, get) => ({
       servers: updateListItem([...get().servers], server, () => ({
         ...server,
       })),
+      lanServers: updateListItem([...get().lanServers], server, () => ({
+        ...server,
+      })),
     }),
+  lanServers: [],
+  setLanServers: (list) => set({ lanServers: list }),
 }));
 
 const usePersistentServers = create<ServersPersistentState>()(
@@ -111,6 +124,7 @@ const usePersistentServers = create<ServersPersistentState>()(
           );
 
           queryServer(server, "favorites", "basic");
+          watchServers("favorites", updated, FAVORITES_WATCH_INTERVAL_MS);
           emitWithDelay("addToFavorites", server);
 
           return { favorites: updated };
@@ -121,6 +135,7 @@ const usePersistentServers = create<ServersPersistentState>()(
           const updated = get().favorites.filter(
             (s) => !isSameServer(s, server)
           );
+          watchServers("favorites", updated, FAVORITES_WATCH_INTERVAL_MS);
           emitWithDelay("removeFromFavorites", server);
           return { favorites: updated };
         }),
@@ -180,6 +195,32 @@ const usePersistentServers = create<ServersPersistentState>()(
   )
 );
 
+// the `favorites` command line commands read this copy, the command line
+// can't reach the webview's storage. query results replace the favorites
+// array too, so only write it when what ends up in the file changed
+let lastSyncedFavorites: string | undefined;
+
+const syncFavorites = (favorites: Server[]) => {
+  const servers = favorites.map(({ ip, port, hostname, password }) => ({
+    ip,
+    port,
+    name: hostname,
+    password: password || "",
+  }));
+  const synced = JSON.stringify(servers);
+  if (synced === lastSyncedFavorites) return;
+
+  lastSyncedFavorites = synced;
+  invoke("sync_favorites", { servers }).catch((e) => {
+    lastSyncedFavorites = undefined;
+    Log.debug("[servers.ts: syncFavorites]", e);
+  });
+};
+
+usePersistentServers.subscribe((state, prev) => {
+  if (state.favorites !== prev.favorites) syncFavorites(state.favorites);
+});
+
 [
   "updateInFavoritesList",
   "addToFavorites",
@@ -195,4 +236,4 @@ const usePersistentServers = create<ServersPersistentState>()(
   })
 );
 
-export { usePersistentServers, useServers };
+export { syncFavorites, usePersistentServers, useServers };
@@ -1,8 +1,10 @@
+import { invoke } from "@tauri-apps/api";
 import { emit, listen } from "@tauri-apps/api/event";
 import { appWindow } from "@tauri-apps/api/window";
 import { create } from "zustand";
 import { createJSONStorage, persist } from "zustand/middleware";
 import { stateStorage } from "../utils/stateStorage";
+import { Log } from "../utils/logger";
 import { SAMPDLLVersions } from "../utils/types";
 
 const MAX_RECENT_NICKNAMES = 5;
@@ -12,6 +14,7 @@ interface SettingsPersistentState {
   gtasaPath: string;
   customGameExe: string;
   sampVersion: SAMPDLLVersions;
+  directLinks: boolean;
   dataMerged: boolean;
   recentNicknames: string[];
   setNickName: (name: string) => void;
@@ -19,6 +22,7 @@ interface SettingsPersistentState {
   setGTASAPath: (path: string) => void;
   setCustomGameExe: (fileName: string) => void;
   setSampVersion: (version: SAMPDLLVersions) => void;
+  setDirectLinks: (enabled: boolean) => void;
 }
 
 const emitWithDelay = (event: string, payload: any) =>
@@ -31,6 +35,7 @@ const useSettings = create<SettingsPersistentState>()(
       gtasaPath: "",
       customGameExe: "",
       sampVersion: "custom",
+      directLinks: false,
       dataMerged: false,
       recentNicknames: [],
       setNickName: (name) =>
@@ -53,6 +58,7 @@ const useSettings = create<SettingsPersistentState>()(
       setGTASAPath: (path) => set({ gtasaPath: path }),
       setCustomGameExe: (fileName) => set({ customGameExe: fileName }),
       setSampVersion: (version) => set({ sampVersion: version }),
+      setDirectLinks: (enabled) => set({ directLinks: enabled }),
     }),
     {
       name: "settings-storage",
@@ -69,4 +75,30 @@ const useSettings = create<SettingsPersistentState>()(
   })
 );
 
+// The launcher reads these before any window opens, e.g. to launch the game
+// straight from an omp:// link, so they are mirrored to the backend.
+const syncedKeys = [
+  "nickName",
+  "gtasaPath",
+  "sampVersion",
+  "customGameExe",
+  "directLinks",
+] as const;
+
+const syncLauncherSettings = (state: SettingsPersistentState) => {
+  const settings = Object.fromEntries(
+    syncedKeys.map((key) => [key, state[key]])
+  );
+  invoke("set_launcher_settings", { settings }).catch((e) =>
+    Log.warn("Failed to store launcher settings:", e)
+  );
+};
+
+syncLauncherSettings(useSettings.getState());
+useSettings.subscribe((state, prev) => {
+  if (syncedKeys.some((key) => state[key] !== prev[key])) {
+    syncLauncherSettings(state);
+  }
+});
+
 export { useSettings };
@@ -18,7 +18,7 @@ import { fetchServers, getIpAddress } from "../utils/helpers";
 import { Log } from "./logger";
 import { PING_TIMEOUT_VALUE } from "./query";
 import { sc } from "./sizeScaler";
-import { Server } from "./types";
+import { SAMPDLLVersions, Server } from "./types";
 
 const showOkModal = (title: string, description: string) => {
   const { showMessageBox, hideMessageBox } = useMessageBox.getState();
@@ -64,23 +64,28 @@ export const startGame = async (
   server: Server,
   nickname: string,
   gtasaPath: string,
-  password: string
+  password: string,
+  // overrides the version from the settings, e.g. one picked by a link
+  version?: SAMPDLLVersions
 ) => {
   const { addToRecentlyJoined } = usePersistentServers.getState();
   const { showMessageBox, hideMessageBox } = useMessageBox.getState();
   const { show: showSettings } = useSettingsModal.getState();
-  const { sampVersion, customGameExe } = useSettings.getState();
+  const { customGameExe } = useSettings.getState();
+  const sampVersion = version ?? useSettings.getState().sampVersion;
   const { showPrompt, setServer } = useJoinServerPrompt.getState();
   const { setSelected } = useServers.getState();
 
   if (IN_GAME) {
     invoke("send_message_to_game", {
       id: IN_GAME_PROCESS_ID,
-      message: password.length
-        ? `connect:${await getIpAddress(server.ip)}:${
-            server.port
-          }:${nickname}:${password}`
-        : `connect:${await getIpAddress(server.ip)}:${server.port}:${nickname}`,
+      message: {
+        type: "connect",
+        host: await getIpAddress(server.ip),
+        port: server.port,
+        nickname,
+        password: password.length ? password : null,
+      },
     });
     useSettings.getState().addRecentNickname(nickname);
     return;
@@ -336,6 +341,54 @@ export const exportFavoriteListFile = async () => {
   }
 };
 
+interface FavoriteListEntry {
+  ip: string;
+  port: number | string;
+  name?: string;
+  password?: string;
+}
+
+const addFavoriteListEntries = (servers: FavoriteListEntry[]) => {
+  const { addToFavorites } = usePersistentServers.getState();
+
+  servers.forEach((srv) => {
+    if (srv.ip && srv.port) {
+      addToFavorites({
+        ip: srv.ip,
+        port: Number(srv.port),
+        hostname: srv.name || `${srv.ip}:${srv.port}`,
+        playerCount: 0,
+        maxPlayers: 0,
+        gameMode: "-",
+        language: "-",
+        hasPassword: !!srv.password,
+        version: "-",
+        usingOmp: false,
+        partner: false,
+        ping: PING_TIMEOUT_VALUE,
+        players: [],
+        password: srv.password || "",
+        rules: {} as Server["rules"],
+      });
+    }
+  });
+};
+
+// servers queued from the command line with `favorites import`
+export const importPendingFavorites = async () => {
+  try {
+    const servers = await invoke<FavoriteListEntry[]>(
+      "take_pending_favorites"
+    );
+    if (!servers.length) return;
+
+    addFavoriteListEntries(servers);
+    fetchServers(true);
+  } catch (error) {
+    Log.debug("Error importing pending favorites:", error);
+  }
+};
+
 export const importFavoriteListFile = async () => {
   try {
     const selected = await open({
@@ -351,29 +404,7 @@ export const importFavoriteListFile = async () => {
       throw new Error("Invalid file format: missing servers array");
     }
 
-    const { addToFavorites } = usePersistentServers.getState();
-
-    data.servers.forEach((srv: any) => {
-      if (srv.ip && srv.port) {
-        addToFavorites({
-          ip: srv.ip,
-          port: Number(srv.port),
-          hostname: srv.name || `${srv.ip}:${srv.port}`,
-          playerCount: 0,
-          maxPlayers: 0,
-          gameMode: "-",
-          language: "-",
-          hasPassword: !!srv.password,
-          version: "-",
-          usingOmp: false,
-          partner: false,
-          ping: PING_TIMEOUT_VALUE,
-          players: [],
-          password: srv.password || "",
-          rules: {} as Server["rules"],
-        });
-      }
-    });
+    addFavoriteListEntries(data.servers);
 
     fetchServers(true);
     useNotification
@@ -6,11 +6,15 @@ import { getUpdateInfo } from "../api/apis";
 import { useAppState } from "../states/app";
 import { useMessageBox } from "../states/messageModal";
 import { usePersistentServers } from "../states/servers";
-import { chunk } from "./array";
 import { Log } from "./logger";
-import { PING_TIMEOUT_VALUE, queryServer } from "./query";
 import {
-  APIResponseServer,
+  FAVORITES_WATCH_INTERVAL_MS,
+  PING_TIMEOUT_VALUE,
+  queryServers,
+  watchServers,
+} from "./query";
+import {
+  MasterListServer,
   SAMPDLLVersions,
   SAMP_DLL_VERSIONS,
   SearchData,
@@ -19,13 +23,6 @@ import {
 } from "./types";
 import { validateServerAddressIPv4 } from "./validation";
 
-// Server update configuration
-const SERVER_UPDATE_CONFIG = {
-  BATCH_SIZE: 2,
-  BATCH_DELAY: 2000,
-  INITIAL_DELAY: 500,
-} as const;
-
 // Language filter configuration
 interface LanguageFilter {
   readonly name: string;
@@ -34,31 +31,17 @@ interface LanguageFilter {
 
 export const languageFilters: LanguageFilter[] = [];
 
-export const mapAPIResponseServerListToAppStructure = (
-  list: readonly APIResponseServer[]
+export const mapMasterListToAppStructure = (
+  list: readonly MasterListServer[]
 ): Server[] => {
-  return list.map((server): Server => {
-    const [ip, portStr] = server.core.ip.split(":");
-    const port = parseInt(portStr, 10);
-
-    return {
-      hostname: server.core.hn,
-      gameMode: server.core.gm,
-      ip,
-      port: isNaN(port) ? 7777 : port,
-      language: server.core.la,
-      hasPassword: server.core.pa,
-      playerCount: server.core.pc,
-      maxPlayers: server.core.pm,
-      version: server.core.vn,
-      rules: server.ru,
+  return list.map(
+    (server): Server => ({
+      ...server,
       players: [],
       ping: PING_TIMEOUT_VALUE,
       password: "",
-      usingOmp: server.core.omp,
-      partner: server.core.pr,
-    };
-  });
+    })
+  );
 };
 
 const updateServersInBatches = (
@@ -67,17 +50,9 @@ const updateServersInBatches = (
 ): void => {
   if (!servers.length) return;
 
-  const batches = chunk(servers, SERVER_UPDATE_CONFIG.BATCH_SIZE);
-
-  batches.forEach((batch, batchIndex) => {
-    setTimeout(() => {
-      batch.forEach((server) => {
-        if (server) {
-          queryServer(server, listType, "basic");
-        }
-      });
-    }, SERVER_UPDATE_CONFIG.INITIAL_DELAY + batchIndex * SERVER_UPDATE_CONFIG.BATCH_DELAY);
-  });
+  // the whole list goes out as one batch, the native side limits how many
+  // requests are in flight and streams every result back as it arrives
+  queryServers(servers, listType, "basic");
 };
 
 export const fetchServers = async (cached: boolean = true): Promise<void> => {
@@ -91,6 +66,7 @@ export const fetchServers = async (cached: boolean = true): Promise<void> => {
     const { favorites } = usePersistentServers.getState();
     if (Array.isArray(favorites) && favorites.length > 0) {
       updateServersInBatches(favorites, "favorites");
+      watchServers("favorites", favorites, FAVORITES_WATCH_INTERVAL_MS);
     }
 
     // Fetch and set servers through the API
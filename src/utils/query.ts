@@ -1,17 +1,45 @@
 import { invoke } from "@tauri-apps/api";
+import { listen } from "@tauri-apps/api/event";
 import { usePersistentServers, useServers } from "../states/servers";
 import { Log } from "./logger";
-import { ListType, Server } from "./types";
+import { ListType, Player, Server } from "./types";
 
 export const PING_TIMEOUT_VALUE = 9999;
+export const FAVORITES_WATCH_INTERVAL_MS = 15000;
 const DEFAULT_PING_VALUE = 0;
 
+type QueryErrorKind =
+  | "timeout"
+  | "network"
+  | "resolve"
+  | "invalid_response"
+  | "rate_limited"
+  | "internal";
+
+interface QueryError {
+  kind: QueryErrorKind;
+  message: string;
+}
+
+type QueryOutcome<T> = { ok: T } | { error: QueryError };
+
+type QuerySection = "info" | "extra_info" | "players" | "rules" | "ping";
+
 interface QueryResult {
-  info?: string;
-  players?: string;
-  rules?: string;
-  extra_info?: string;
+  info?: QueryOutcome<ServerInfo>;
+  players?: QueryOutcome<Player[]>;
+  rules?: QueryOutcome<[string, string][]>;
+  extra_info?: QueryOutcome<OmpExtraInfo>;
   ping?: number;
+  // sections answered from the cache, queried again in the background
+  stale?: QuerySection[];
+}
+
+interface BatchQueryResult {
+  batch_id: number;
+  ip: string;
+  port: number;
+  result: QueryResult;
 }
 
 interface ServerInfo {
@@ -21,7 +49,6 @@ interface ServerInfo {
   hostname: string;
   gamemode: string;
   language: string;
-  error?: boolean;
 }
 
 interface OmpExtraInfo {
@@ -29,7 +56,6 @@ interface OmpExtraInfo {
   dark_banner_url?: string;
   discord_link?: string;
   logo_url?: string;
-  error?: boolean;
 }
 
 interface QueryConfig {
@@ -95,17 +121,15 @@ const queryServerImpl = async (
   listType: ListType
 ): Promise<void> => {
   try {
-    const result: QueryResult = JSON.parse(
-      await invoke("query_server", {
-        ip,
-        port,
-        info: config.info,
-        extraInfo: config.extraInfo,
-        players: config.players,
-        rules: config.rules,
-        ping: config.ping,
-      })
-    );
+    const result: QueryResult = await invoke("query_server", {
+      ip,
+      port,
+      info: config.info,
+      extraInfo: config.extraInfo,
+      players: config.players,
+      rules: config.rules,
+      ping: config.ping,
+    });
 
     await processQueryResult(ip, port, result, listType);
   } catch (e) {
@@ -113,51 +137,170 @@ const queryServerImpl = async (
   }
 };
 
-const parseJsonResponse = <T>(jsonString: string): T | null => {
+let nextBatchId = 0;
+
+export const queryServers = async (
+  servers: Server[],
+  listType: ListType = "internet",
+  queryType: "all" | "basic" = "basic"
+): Promise<void> => {
+  if (!servers.length) return;
+
+  const batchId = ++nextBatchId;
+  const unlisten = await listen<BatchQueryResult>(
+    "server-query-result",
+    (event) => {
+      const { batch_id, ip, port, result } = event.payload;
+      if (batch_id === batchId) {
+        processQueryResult(ip, port, result, listType);
+      }
+    }
+  );
+
   try {
-    return JSON.parse(jsonString);
-  } catch {
-    return null;
+    await invoke("query_servers", {
+      batchId,
+      servers: servers.map(({ ip, port }) => ({ ip, port })),
+      params: createQueryConfig(queryType),
+    });
+  } catch (e) {
+    Log.debug("[query.ts: queryServers]", e);
+  } finally {
+    unlisten();
+  }
+};
+
+interface ServerUpdate {
+  ip: string;
+  port: number;
+  online: boolean;
+  info: ServerInfo | null;
+}
+
+// the native side re-queries watched servers on its own and only reports
+// changes, so updates keep coming while the window is hidden
+export const watchServers = async (
+  name: string,
+  servers: Server[],
+  intervalMs?: number
+): Promise<void> => {
+  try {
+    await invoke("watch_servers", {
+      name,
+      servers: servers.map(({ ip, port }) => ({ ip, port })),
+      intervalMs,
+    });
+  } catch (e) {
+    Log.debug("[query.ts: watchServers]", e);
   }
 };
 
+export const unwatchServers = async (name: string): Promise<void> => {
+  try {
+    await invoke("unwatch_servers", { name });
+  } catch (e) {
+    Log.debug("[query.ts: unwatchServers]", e);
+  }
+};
+
+export const listenToServerUpdates = (listType: ListType = "favorites") =>
+  listen<ServerUpdate>("server-updated", async (event) => {
+    const { ip, port, online, info } = event.payload;
+    if (online && info) {
+      await setServerInfo(ip, port, info, listType);
+    } else {
+      await setServerPing(ip, port, PING_TIMEOUT_VALUE, listType);
+    }
+  });
+
+interface LanServer {
+  scan_id: number;
+  ip: string;
+  port: number;
+  info: ServerInfo;
+}
+
+let nextScanId = 0;
+let lanScanRunning = false;
+
+const lanServerToAppStructure = ({ ip, port, info }: LanServer): Server => ({
+  ip,
+  port,
+  hostname: info.hostname,
+  gameMode: info.gamemode,
+  language: info.language,
+  hasPassword: info.password,
+  playerCount: info.players,
+  maxPlayers: info.max_players,
+  version: "",
+  usingOmp: false,
+  partner: false,
+  ping: PING_TIMEOUT_VALUE,
+  players: [],
+  password: "",
+  rules: {} as Server["rules"],
+});
+
+// servers show up in the list as they answer, the returned promise resolves
+// once the whole scan is done
+export const scanLan = async (): Promise<void> => {
+  if (lanScanRunning) return;
+  lanScanRunning = true;
+
+  const scanId = ++nextScanId;
+  const { setLanServers } = useServers.getState();
+  setLanServers([]);
+
+  const unlisten = await listen<LanServer>("lan-server-found", (event) => {
+    if (event.payload.scan_id !== scanId) return;
+    const { lanServers } = useServers.getState();
+    setLanServers([...lanServers, lanServerToAppStructure(event.payload)]);
+  });
+
+  try {
+    const found = await invoke<LanServer[]>("scan_lan", { scanId });
+    setLanServers(found.map(lanServerToAppStructure));
+    queryServers(useServers.getState().lanServers, "lan", "basic");
+  } catch (e) {
+    Log.debug("[query.ts: scanLan]", e);
+  } finally {
+    unlisten();
+    lanScanRunning = false;
+  }
+};
+
+const unwrapOutcome = <T>(outcome?: QueryOutcome<T>): T | null => {
+  if (!outcome) return null;
+  if ("ok" in outcome) return outcome.ok;
+
+  Log.debug("[query.ts: unwrapOutcome]", outcome.error);
+  return null;
+};
+
 const processQueryResult = async (
   ip: string,
   port: number,
   result: QueryResult,
   listType: ListType
 ): Promise<void> => {
-  if (result.info) {
-    const parsedInfo = parseJsonResponse<ServerInfo>(result.info);
-    if (parsedInfo && !parsedInfo.error) {
-      await setServerInfo(ip, port, parsedInfo, listType);
-    }
+  const info = unwrapOutcome(result.info);
+  if (info) {
+    await setServerInfo(ip, port, info, listType);
   }
 
-  if (result.players) {
-    const parsedPlayers = parseJsonResponse<any[]>(result.players);
-    if (parsedPlayers) {
-      if (
-        !Array.isArray(parsedPlayers) ||
-        !parsedPlayers.some((p: any) => p.error)
-      ) {
-        await setServerPlayers(ip, port, parsedPlayers, listType);
-      }
-    }
+  const players = unwrapOutcome(result.players);
+  if (players) {
+    await setServerPlayers(ip, port, players, listType);
   }
 
-  if (result.rules) {
-    const parsedRules = parseJsonResponse<any[]>(result.rules);
-    if (parsedRules && !parsedRules.some?.((r: any) => r.error)) {
-      await setServerRules(ip, port, parsedRules, listType);
-    }
+  const rules = unwrapOutcome(result.rules);
+  if (rules) {
+    await setServerRules(ip, port, rules, listType);
   }
 
-  if (result.extra_info) {
-    const parsedExtraInfo = parseJsonResponse<OmpExtraInfo>(result.extra_info);
-    if (parsedExtraInfo && !parsedExtraInfo.error) {
-      await setServerOmpExtraInfo(ip, port, parsedExtraInfo, listType);
-    }
+  const extraInfo = unwrapOutcome(result.extra_info);
+  if (extraInfo) {
+    await setServerOmpExtraInfo(ip, port, extraInfo, listType);
   }
 
   if (result.ping != null && typeof result.ping === "number") {
@@ -194,7 +337,7 @@ const setServerInfo = async (
 const setServerPlayers = async (
   ip: string,
   port: number,
-  res: any[],
+  res: Player[],
   listType: ListType
 ): Promise<void> => {
   try {
@@ -297,7 +440,7 @@ const setServerPing = async (
 };
 
 const getListBasedOnType = (listType: ListType): Server[] => {
-  const { servers } = useServers.getState();
+  const { servers, lanServers } = useServers.getState();
   const { favorites, recentlyJoined } = usePersistentServers.getState();
 
   switch (listType) {
@@ -308,6 +451,8 @@ const getListBasedOnType = (listType: ListType): Server[] => {
       return favorites;
     case "recentlyjoined":
       return recentlyJoined;
+    case "lan":
+      return lanServers;
     default:
       return servers;
   }
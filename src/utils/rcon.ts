@@ -0,0 +1,40 @@
+import { invoke } from "@tauri-apps/api";
+import { listen } from "@tauri-apps/api/event";
+import { Log } from "./logger";
+
+interface RconOutput {
+  session_id: number;
+  line: string;
+}
+
+let nextSessionId = 0;
+
+export const sendRconCommand = async (
+  ip: string,
+  port: number,
+  password: string,
+  command: string,
+  onLine?: (line: string) => void
+): Promise<string[]> => {
+  const sessionId = ++nextSessionId;
+  const unlisten = await listen<RconOutput>("rcon-output", (event) => {
+    if (event.payload.session_id === sessionId && onLine) {
+      onLine(event.payload.line);
+    }
+  });
+
+  try {
+    return await invoke<string[]>("send_rcon_command", {
+      sessionId,
+      ip,
+      port,
+      password,
+      command,
+    });
+  } catch (e) {
+    Log.debug("[rcon.ts: sendRconCommand]", e);
+    throw e;
+  } finally {
+    unlisten();
+  }
+};
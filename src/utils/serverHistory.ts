@@ -0,0 +1,46 @@
+import { invoke } from "@tauri-apps/api";
+import { Log } from "./logger";
+
+export interface ServerHistorySample {
+  time: number; // unix time in seconds the sample starts at
+  samples: number;
+  online: number; // how many of `samples` the server answered
+  ping: number | null;
+  ping_samples: number;
+  players: number | null;
+  peak_players: number | null;
+}
+
+// `from` and `to` are unix times in seconds, samples are merged into buckets
+// of `resolutionSecs` if given
+export const getServerHistory = async (
+  ip: string,
+  port: number,
+  from?: number,
+  to?: number,
+  resolutionSecs?: number
+): Promise<ServerHistorySample[]> => {
+  try {
+    return await invoke<ServerHistorySample[]>("get_server_history", {
+      ip,
+      port,
+      from,
+      to,
+      resolutionSecs,
+    });
+  } catch (e) {
+    Log.debug("[serverHistory.ts: getServerHistory]", e);
+    return [];
+  }
+};
+
+export const clearServerHistory = async (
+  ip?: string,
+  port?: number
+): Promise<void> => {
+  try {
+    await invoke("clear_server_history", { ip, port });
+  } catch (e) {
+    Log.debug("[serverHistory.ts: clearServerHistory]", e);
+  }
+};
@@ -16,6 +16,7 @@ export const LIST_TYPES = [
   "internet",
   "partners",
   "recentlyjoined",
+  "lan",
 ] as const;
 
 export type ListType = (typeof LIST_TYPES)[number];
@@ -41,6 +42,8 @@ export type SAMPDLLVersions = (typeof SAMP_DLL_VERSIONS)[number];
 export interface Player {
   name: string;
   score: number;
+  id?: number;
+  ping?: number;
 }
 
 export interface ServerRules {
@@ -94,22 +97,16 @@ export interface SearchData {
 }
 
 // API response types
-export interface APIResponseServerCore {
-  gm: string;
-  hn: string;
-  ip: string;
-  la: string;
-  pa: boolean;
-  pc: number;
-  pm: number;
-  vn: string;
-  omp: boolean;
-  pr: boolean;
-}
-
-export interface APIResponseServer {
-  core: APIResponseServerCore;
-  ru: ServerRules;
+// A master list entry as normalized by the native side
+export type MasterListServer = Omit<
+  Server,
+  "ping" | "players" | "password" | "omp"
+>;
+
+export interface MasterList {
+  servers: MasterListServer[];
+  fetched_at: number;
+  stale: boolean;
 }
 
 // Settings types